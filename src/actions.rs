@@ -0,0 +1,208 @@
+use bevy::{
+    input::InputSystem,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+/// How far a stick has to be pushed to count as a button press
+const STICK_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Merges keyboard and gamepad input into [`Action`]s, see [`ActionState`].
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>()
+            .init_resource::<ActionState>()
+            .add_systems(PreUpdate, update_action_state.after(InputSystem));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Duck,
+    Dash,
+    Pause,
+    LoadCheckpoint,
+    Restart,
+}
+
+impl Action {
+    pub const ALL: [Action; 8] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Duck,
+        Action::Dash,
+        Action::Pause,
+        Action::LoadCheckpoint,
+        Action::Restart,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Jump => "Jump",
+            Action::Duck => "Duck",
+            Action::Dash => "Dash",
+            Action::Pause => "Pause",
+            Action::LoadCheckpoint => "Load Checkpoint",
+            Action::Restart => "Restart",
+        }
+    }
+}
+
+/// The keys and gamepad buttons bound to each action. The left stick always moves and ducks.
+#[derive(Resource)]
+pub struct InputMap {
+    pub keys: HashMap<Action, Vec<KeyCode>>,
+    pub gamepad_buttons: HashMap<Action, Vec<GamepadButtonType>>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use GamepadButtonType as Button;
+
+        let bindings = [
+            (
+                Action::MoveLeft,
+                vec![KeyCode::KeyA, KeyCode::ArrowLeft],
+                vec![Button::DPadLeft],
+            ),
+            (
+                Action::MoveRight,
+                vec![KeyCode::KeyD, KeyCode::ArrowRight],
+                vec![Button::DPadRight],
+            ),
+            (
+                Action::Jump,
+                vec![KeyCode::Space, KeyCode::ArrowUp],
+                vec![Button::South],
+            ),
+            (
+                Action::Duck,
+                vec![KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ArrowDown],
+                vec![Button::DPadDown],
+            ),
+            (Action::Dash, vec![KeyCode::KeyX], vec![Button::West]),
+            (Action::Pause, vec![KeyCode::Escape], vec![Button::Start]),
+            (
+                Action::LoadCheckpoint,
+                vec![KeyCode::KeyL],
+                vec![Button::North],
+            ),
+            (Action::Restart, vec![KeyCode::KeyR], vec![Button::Select]),
+        ];
+
+        let mut input_map = Self {
+            keys: HashMap::new(),
+            gamepad_buttons: HashMap::new(),
+        };
+        for (action, keys, gamepad_buttons) in bindings {
+            input_map.keys.insert(action, keys);
+            input_map.gamepad_buttons.insert(action, gamepad_buttons);
+        }
+        input_map
+    }
+}
+
+/// The state of every [`Action`] in the current frame.
+#[derive(Resource, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+    move_axis: f32,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    /// Horizontal movement from -1 (left) to 1 (right). Analog when using a stick.
+    pub fn move_axis(&self) -> f32 {
+        self.move_axis
+    }
+}
+
+pub fn update_action_state(
+    mut action_state: ResMut<ActionState>,
+    input_map: Res<InputMap>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+) {
+    let mut pressed = HashSet::new();
+    let mut stick = Vec2::ZERO;
+
+    for action in Action::ALL {
+        let key_pressed = input_map
+            .keys
+            .get(&action)
+            .is_some_and(|keys| keyboard_input.any_pressed(keys.iter().copied()));
+        let button_pressed = input_map
+            .gamepad_buttons
+            .get(&action)
+            .is_some_and(|buttons| {
+                gamepads.iter().any(|gamepad| {
+                    buttons
+                        .iter()
+                        .any(|button| gamepad_buttons.pressed(GamepadButton::new(gamepad, *button)))
+                })
+            });
+        if key_pressed || button_pressed {
+            pressed.insert(action);
+        }
+    }
+
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            gamepad_axes
+                .get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.)
+        };
+        let gamepad_stick = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        );
+        if gamepad_stick.length_squared() > stick.length_squared() {
+            stick = gamepad_stick;
+        }
+    }
+    if stick.x <= -STICK_PRESS_THRESHOLD {
+        pressed.insert(Action::MoveLeft);
+    }
+    if stick.x >= STICK_PRESS_THRESHOLD {
+        pressed.insert(Action::MoveRight);
+    }
+    if stick.y <= -STICK_PRESS_THRESHOLD {
+        pressed.insert(Action::Duck);
+    }
+
+    // sticks below the threshold are ignored so they don't make the player creep around
+    action_state.move_axis = if stick.x.abs() >= STICK_PRESS_THRESHOLD {
+        stick.x.clamp(-1., 1.)
+    } else {
+        pressed.contains(&Action::MoveRight) as i8 as f32
+            - pressed.contains(&Action::MoveLeft) as i8 as f32
+    };
+
+    action_state.just_pressed = pressed.difference(&action_state.pressed).copied().collect();
+    action_state.just_released = action_state.pressed.difference(&pressed).copied().collect();
+    action_state.pressed = pressed;
+}
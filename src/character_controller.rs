@@ -1,48 +1,241 @@
+use std::time::Duration;
+
 use avian2d::math::{Scalar, Vector};
 use avian2d::prelude::*;
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{Action, ActionState},
+    player_alive, IsPaused, PlayerState,
+};
 
-use crate::IsPaused;
+pub const DEFAULT_COYOTE_TIME: Duration = Duration::from_millis(100);
+pub const DEFAULT_JUMP_BUFFER: Duration = Duration::from_millis(100);
 
 pub struct CharacterControllerPlugin;
 
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<MovementAction>().add_systems(
-            Update,
-            (
-                (keyboard_input, update_grounded, update_ducking),
-                movement,
-                // apply_movement_damping,
+        app.add_event::<MovementAction>()
+            .add_event::<JumpEvent>()
+            .init_resource::<DuckInput>()
+            .add_systems(
+                Update,
+                player_input
+                    .in_set(PlayerInputSet)
+                    .run_if(in_state(IsPaused::Unpaused))
+                    .run_if(in_state(PlayerState::Alive)),
             )
-                .chain()
-                .run_if(in_state(IsPaused::Unpaused)),
-        );
+            // a fixed timestep makes the movement independent of the frame rate
+            .add_systems(
+                FixedUpdate,
+                (
+                    (update_grounded, update_touching_wall, update_ducking),
+                    movement,
+                    dash,
+                    apply_fall_gravity,
+                    // apply_movement_damping,
+                )
+                    .chain()
+                    .run_if(in_state(IsPaused::Unpaused))
+                    .run_if(player_alive),
+            );
     }
 }
 
-#[derive(Event)]
+/// Systems that turn the player's input into [`MovementAction`]s and other gameplay events.
+/// Replays disable it to feed in the recorded input instead.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerInputSet;
+
+#[derive(Event, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum MovementAction {
     Move(Scalar),
     Jump,
+    /// The jump button was released
+    JumpRelease,
+    Dash,
+}
+
+/// Whether the character should duck. Unlike [`MovementAction`]s, this is a state that is held.
+#[derive(Resource, Default)]
+pub struct DuckInput(pub bool);
+
+/// Sent when a character jumps off the ground or a wall.
+#[derive(Event)]
+pub struct JumpEvent {
+    pub entity: Entity,
 }
 
 #[derive(Component)]
 #[component(storage = "SparseSet")]
 pub struct Grounded;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallSide {
+    Left,
+    Right,
+}
+
+impl WallSide {
+    /// The horizontal direction pointing towards the wall
+    pub fn direction(self) -> Scalar {
+        match self {
+            WallSide::Left => -1.,
+            WallSide::Right => 1.,
+        }
+    }
+}
+
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct TouchingWall {
+    pub side: WallSide,
+}
+
 #[derive(Component)]
 pub struct Ducking;
 
 #[derive(Component)]
 pub struct CharacterController;
 
+/// Maximum horizontal speed the character can reach by moving, in u/s
 #[derive(Component)]
-pub struct MovementSpeed(Scalar);
+pub struct MaxSpeed(pub Scalar);
+
+/// How fast the character speeds up while moving, in u/s²
+#[derive(Component)]
+pub struct Acceleration {
+    pub ground: Scalar,
+    pub air: Scalar,
+}
+
+/// How fast the character slows down without any input, in u/s²
+#[derive(Component)]
+pub struct Deceleration {
+    pub ground: Scalar,
+    pub air: Scalar,
+}
+
+/// The last horizontal direction sent with [`MovementAction::Move`]. Kept around since input
+/// events don't arrive on every fixed timestep.
+#[derive(Component, Default)]
+pub struct MovementDirection(Scalar);
 
 #[derive(Component)]
 pub struct JumpImpulse(Scalar);
 
+/// Multiplies the upward velocity when the jump button is released early, so short presses result
+/// in lower jumps.
+#[derive(Component)]
+pub struct JumpCut(pub Scalar);
+
+/// Gravity scale used while the character is falling. Making falls faster than the ascent makes
+/// jumps feel less floaty.
+#[derive(Component)]
+pub struct FallGravityMultiplier(pub Scalar);
+
+/// Lets the character slide down walls and jump off them.
+#[derive(Component)]
+pub struct WallJump {
+    /// Maximum fall speed while pushing against a wall
+    pub slide_speed: Scalar,
+    /// Velocity of a wall jump, with the X component pointing away from the wall
+    pub impulse: Vector,
+    /// How long horizontal input is ignored after a wall jump, so the character actually moves
+    /// away from the wall
+    pub control_lock: Duration,
+}
+
+impl Default for WallJump {
+    fn default() -> Self {
+        Self {
+            slide_speed: 100.,
+            impulse: Vector::new(300., 400.),
+            control_lock: Duration::from_millis(150),
+        }
+    }
+}
+
+/// Lets the character dash horizontally, see [`DashBundle`].
+#[derive(Component)]
+pub struct Dash {
+    pub distance: Scalar,
+    pub duration: Duration,
+    /// Time after a dash ends before the next one can start
+    pub cooldown: Duration,
+    /// How many dashes can be used before touching the ground again
+    pub air_dashes: u32,
+}
+
+impl Default for Dash {
+    fn default() -> Self {
+        Self {
+            distance: 150.,
+            duration: Duration::from_millis(150),
+            cooldown: Duration::from_millis(300),
+            air_dashes: 1,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct DashState {
+    /// `None` if the character hasn't dashed yet
+    time_since_dash: Option<Duration>,
+    air_dashes_used: u32,
+    /// The horizontal direction the character is facing
+    direction: Scalar,
+}
+
+impl Default for DashState {
+    fn default() -> Self {
+        Self {
+            time_since_dash: None,
+            air_dashes_used: 0,
+            direction: 1.,
+        }
+    }
+}
+
+impl DashState {
+    pub fn is_dashing(&self, dash: &Dash) -> bool {
+        self.time_since_dash
+            .is_some_and(|time| time < dash.duration)
+    }
+}
+
+/// Optional ability that is not part of [`CharacterControllerBundle`].
+#[derive(Bundle, Default)]
+pub struct DashBundle {
+    dash: Dash,
+    dash_state: DashState,
+}
+
+/// How long after leaving the ground the character is still allowed to jump.
+#[derive(Component)]
+pub struct CoyoteTime(pub Duration);
+
+/// How long a jump press is remembered while the character can't jump yet, e.g. right before
+/// landing.
+#[derive(Component)]
+pub struct JumpBuffer(pub Duration);
+
+/// Timing information needed for [`CoyoteTime`] and [`JumpBuffer`].
+#[derive(Component, Default)]
+pub struct JumpState {
+    /// Time since the character was last on the ground. `None` if it has jumped since then.
+    time_since_grounded: Option<Duration>,
+    /// Time since jump was pressed. `None` if there is no buffered jump.
+    time_since_jump_pressed: Option<Duration>,
+    jump_held: bool,
+    /// Time since the last wall jump. `None` if horizontal input isn't locked.
+    time_since_wall_jump: Option<Duration>,
+    /// Whether the character is moving up from a jump that can still be cut short
+    rising: bool,
+}
+
 /// The maximum angle a slope can have for the character controller to be able to climb and jump.
 /// If the slope is steeper than this angle, the character will slide down.
 #[derive(Component)]
@@ -50,16 +243,46 @@ pub struct MaxSlopeAngle(Scalar);
 
 #[derive(Bundle)]
 pub struct MovementBundle {
-    acceleration: MovementSpeed,
+    max_speed: MaxSpeed,
+    acceleration: Acceleration,
+    deceleration: Deceleration,
+    direction: MovementDirection,
     jump_impulse: JumpImpulse,
+    jump_cut: JumpCut,
+    fall_gravity: FallGravityMultiplier,
+    gravity_scale: GravityScale,
+    coyote_time: CoyoteTime,
+    jump_buffer: JumpBuffer,
+    jump_state: JumpState,
     max_slope_angle: MaxSlopeAngle,
 }
 
 impl MovementBundle {
-    pub const fn new(speed: Scalar, jump_impulse: Scalar, max_slope_angle: Scalar) -> Self {
+    pub const fn new(max_speed: Scalar, jump_impulse: Scalar, max_slope_angle: Scalar) -> Self {
         Self {
-            acceleration: MovementSpeed(speed),
+            max_speed: MaxSpeed(max_speed),
+            acceleration: Acceleration {
+                ground: 2500.,
+                air: 1500.,
+            },
+            deceleration: Deceleration {
+                ground: 3000.,
+                air: 600.,
+            },
+            direction: MovementDirection(0.),
             jump_impulse: JumpImpulse(jump_impulse),
+            jump_cut: JumpCut(0.5),
+            fall_gravity: FallGravityMultiplier(1.5),
+            gravity_scale: GravityScale(1.),
+            coyote_time: CoyoteTime(DEFAULT_COYOTE_TIME),
+            jump_buffer: JumpBuffer(DEFAULT_JUMP_BUFFER),
+            jump_state: JumpState {
+                time_since_grounded: None,
+                time_since_jump_pressed: None,
+                jump_held: false,
+                time_since_wall_jump: None,
+                rising: false,
+            },
             max_slope_angle: MaxSlopeAngle(max_slope_angle),
         }
     }
@@ -67,7 +290,7 @@ impl MovementBundle {
 
 impl Default for MovementBundle {
     fn default() -> Self {
-        Self::new(15000.0, 400.0, (30.0 as Scalar).to_radians())
+        Self::new(250.0, 400.0, (30.0 as Scalar).to_radians())
     }
 }
 
@@ -98,19 +321,24 @@ impl CharacterControllerBundle {
     }
 }
 
-fn keyboard_input(
+fn player_input(
     mut movement_event_writer: EventWriter<MovementAction>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut duck_input: ResMut<DuckInput>,
+    action_state: Res<ActionState>,
 ) {
-    let left = keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]);
-    let right = keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]);
+    duck_input.0 = action_state.pressed(Action::Duck);
+    movement_event_writer.send(MovementAction::Move(action_state.move_axis() as Scalar));
 
-    let horizontal = right as i8 - left as i8;
-    movement_event_writer.send(MovementAction::Move(horizontal as Scalar));
-
-    if keyboard_input.any_pressed([KeyCode::Space, KeyCode::ArrowUp]) {
+    if action_state.just_pressed(Action::Jump) {
         movement_event_writer.send(MovementAction::Jump);
     }
+    if action_state.just_released(Action::Jump) {
+        movement_event_writer.send(MovementAction::JumpRelease);
+    }
+
+    if action_state.just_pressed(Action::Dash) {
+        movement_event_writer.send(MovementAction::Dash);
+    }
 }
 
 fn update_grounded(
@@ -137,13 +365,49 @@ fn update_grounded(
     }
 }
 
+fn update_touching_wall(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform, &Rotation, &Collider), With<WallJump>>,
+    rigid_bodies: Query<(), With<RigidBody>>,
+    spatial_query: SpatialQuery,
+) {
+    for (entity, transform, rotation, collider) in &query {
+        let mut cast_collider = collider.clone();
+        cast_collider.set_scale(cast_collider.scale() * 0.99, 10);
+
+        let touching_side = [WallSide::Left, WallSide::Right].into_iter().find(|side| {
+            let direction = Dir2::new(Vector::X * side.direction()).unwrap();
+            spatial_query
+                .shape_hits(
+                    &cast_collider,
+                    transform.translation.xy(),
+                    0.,
+                    direction,
+                    2.,
+                    4,
+                    true,
+                    SpatialQueryFilter::from_excluded_entities([entity]),
+                )
+                .iter()
+                // spikes and checkpoints don't have a rigid body and aren't walls
+                .filter(|hit| rigid_bodies.contains(hit.entity))
+                .any(|hit| (rotation * -hit.normal2).x.abs() > 0.7)
+        });
+
+        if let Some(side) = touching_side {
+            commands.entity(entity).insert(TouchingWall { side });
+        } else {
+            commands.entity(entity).remove::<TouchingWall>();
+        }
+    }
+}
+
 fn update_ducking(
     mut commands: Commands,
     mut query: Query<(Entity, &mut Transform, &Collider, Has<Ducking>), With<CharacterController>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    duck_input: Res<DuckInput>,
     spatial_query: SpatialQuery,
 ) {
-    let duck_keys = [KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ArrowDown];
     for (controller, mut transform, collider, is_ducking) in &mut query {
         // maybe this is cool because the calculation is lazy and shit but idk
         let height = |transform: &Mut<Transform>| {
@@ -152,7 +416,7 @@ fn update_ducking(
                 .size()
                 .y
         };
-        if keyboard_input.any_pressed(duck_keys) {
+        if duck_input.0 {
             if !is_ducking {
                 commands.entity(controller).insert(Ducking);
                 transform.scale = Vec3::new(1., 0.5, 1.);
@@ -185,29 +449,219 @@ fn update_ducking(
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn movement(
     time: Res<Time>,
     mut movement_event_reader: EventReader<MovementAction>,
+    mut jump_event_writer: EventWriter<JumpEvent>,
     mut controllers: Query<(
-        &MovementSpeed,
+        Entity,
+        &MaxSpeed,
+        &Acceleration,
+        &Deceleration,
+        &mut MovementDirection,
         &JumpImpulse,
+        &JumpCut,
+        &CoyoteTime,
+        &JumpBuffer,
+        &mut JumpState,
         &mut LinearVelocity,
+        Option<&WallJump>,
+        Option<&TouchingWall>,
         Has<Grounded>,
         Has<Ducking>,
     )>,
 ) {
-    for event in movement_event_reader.read() {
-        for (speed, jump_impulse, mut velocity, is_grounded, is_ducking) in &mut controllers {
+    let events: Vec<_> = movement_event_reader.read().collect();
+
+    for (
+        entity,
+        max_speed,
+        acceleration,
+        deceleration,
+        mut direction,
+        jump_impulse,
+        jump_cut,
+        coyote_time,
+        jump_buffer,
+        mut jump_state,
+        mut velocity,
+        wall_jump,
+        touching_wall,
+        is_grounded,
+        is_ducking,
+    ) in &mut controllers
+    {
+        // don't count as grounded while still moving up from a jump, the ground caster might
+        // still hit the ground for a few frames
+        if is_grounded && velocity.y <= 0. {
+            jump_state.time_since_grounded = Some(Duration::ZERO);
+        } else if let Some(time_since_grounded) = &mut jump_state.time_since_grounded {
+            *time_since_grounded += time.delta();
+        }
+        if let Some(time_since_jump_pressed) = &mut jump_state.time_since_jump_pressed {
+            *time_since_jump_pressed += time.delta();
+        }
+        jump_state.rising &= velocity.y > 0.;
+        if let Some(time_since_wall_jump) = &mut jump_state.time_since_wall_jump {
+            *time_since_wall_jump += time.delta();
+        }
+        let control_locked = jump_state
+            .time_since_wall_jump
+            .zip(wall_jump)
+            .is_some_and(|(time, wall_jump)| time <= wall_jump.control_lock);
+        if !control_locked {
+            jump_state.time_since_wall_jump = None;
+        }
+
+        for event in &events {
             match event {
-                MovementAction::Move(direction) => {
-                    velocity.x = *direction * speed.0 * time.delta_seconds()
-                }
+                MovementAction::Move(new_direction) => direction.0 = *new_direction,
+                MovementAction::Dash => {}
                 MovementAction::Jump => {
-                    if is_grounded && !is_ducking {
-                        velocity.y = jump_impulse.0;
+                    jump_state.time_since_jump_pressed = Some(Duration::ZERO);
+                    jump_state.jump_held = true;
+                }
+                MovementAction::JumpRelease => {
+                    jump_state.jump_held = false;
+                    if jump_state.rising {
+                        velocity.y *= jump_cut.0;
+                        jump_state.rising = false;
                     }
                 }
             }
         }
+
+        if !control_locked {
+            let (acceleration, deceleration) = if is_grounded {
+                (acceleration.ground, deceleration.ground)
+            } else {
+                (acceleration.air, deceleration.air)
+            };
+            let rate = if direction.0 != 0. {
+                acceleration
+            } else {
+                deceleration
+            };
+            let max_delta = rate * time.delta_seconds();
+            let target = direction.0 * max_speed.0;
+            velocity.x += (target - velocity.x).clamp(-max_delta, max_delta);
+        }
+
+        // slide down walls slowly while pushing against them
+        if let (Some(wall_jump), Some(touching_wall)) = (wall_jump, touching_wall) {
+            let pushing_into_wall = direction.0 * touching_wall.side.direction() > 0.;
+            if pushing_into_wall && !is_grounded {
+                velocity.y = velocity.y.max(-wall_jump.slide_speed);
+            }
+        }
+
+        let can_jump = jump_state
+            .time_since_grounded
+            .is_some_and(|time| time <= coyote_time.0);
+        let wants_jump = jump_state
+            .time_since_jump_pressed
+            .is_some_and(|time| time <= jump_buffer.0);
+        if !wants_jump || is_ducking {
+            continue;
+        }
+
+        if can_jump {
+            jump_state.time_since_grounded = None;
+            jump_state.time_since_jump_pressed = None;
+            // a buffered jump whose button was already released is cut right away
+            if jump_state.jump_held {
+                velocity.y = jump_impulse.0;
+                jump_state.rising = true;
+            } else {
+                velocity.y = jump_impulse.0 * jump_cut.0;
+            }
+            jump_event_writer.send(JumpEvent { entity });
+        } else if let (Some(wall_jump), Some(touching_wall), false) =
+            (wall_jump, touching_wall, is_grounded)
+        {
+            jump_state.time_since_jump_pressed = None;
+            jump_state.time_since_wall_jump = Some(Duration::ZERO);
+            jump_state.rising = jump_state.jump_held;
+            velocity.x = -touching_wall.side.direction() * wall_jump.impulse.x;
+            velocity.y = wall_jump.impulse.y;
+            jump_event_writer.send(JumpEvent { entity });
+        }
+    }
+}
+
+fn dash(
+    time: Res<Time>,
+    mut movement_event_reader: EventReader<MovementAction>,
+    mut controllers: Query<(
+        &Dash,
+        &mut DashState,
+        &MaxSpeed,
+        &mut LinearVelocity,
+        Has<Grounded>,
+    )>,
+) {
+    let events: Vec<_> = movement_event_reader.read().collect();
+
+    for (dash, mut dash_state, max_speed, mut velocity, is_grounded) in &mut controllers {
+        let was_dashing = dash_state.is_dashing(dash);
+        if let Some(time_since_dash) = &mut dash_state.time_since_dash {
+            *time_since_dash += time.delta();
+        }
+        // don't keep gliding at dash speed after the dash
+        if was_dashing && !dash_state.is_dashing(dash) {
+            velocity.x = velocity.x.clamp(-max_speed.0, max_speed.0);
+        }
+        if is_grounded && !dash_state.is_dashing(dash) {
+            dash_state.air_dashes_used = 0;
+        }
+
+        let mut dash_pressed = false;
+        for event in &events {
+            match event {
+                MovementAction::Move(direction) if *direction != 0. => {
+                    dash_state.direction = direction.signum()
+                }
+                MovementAction::Dash => dash_pressed = true,
+                _ => {}
+            }
+        }
+
+        let cooled_down = dash_state
+            .time_since_dash
+            .is_none_or(|time| time >= dash.duration + dash.cooldown);
+        let has_dash_left = is_grounded || dash_state.air_dashes_used < dash.air_dashes;
+        if dash_pressed && cooled_down && has_dash_left {
+            dash_state.time_since_dash = Some(Duration::ZERO);
+            if !is_grounded {
+                dash_state.air_dashes_used += 1;
+            }
+        }
+
+        if dash_state.is_dashing(dash) {
+            velocity.x = dash_state.direction * dash.distance / dash.duration.as_secs_f32();
+            velocity.y = 0.;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn apply_fall_gravity(
+    mut controllers: Query<(
+        &FallGravityMultiplier,
+        &LinearVelocity,
+        &mut GravityScale,
+        Option<(&Dash, &DashState)>,
+    )>,
+) {
+    for (fall_gravity, velocity, mut gravity_scale, dash) in &mut controllers {
+        let is_dashing = dash.is_some_and(|(dash, dash_state)| dash_state.is_dashing(dash));
+        gravity_scale.0 = if is_dashing {
+            0.
+        } else if velocity.y < 0. {
+            fall_gravity.0
+        } else {
+            1.
+        };
     }
 }
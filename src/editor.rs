@@ -0,0 +1,895 @@
+use bevy::{
+    asset::io::file::FileAssetReader,
+    color::palettes::css::*,
+    input::mouse::{MouseMotion, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use ron::ser::PrettyConfig;
+
+use crate::{
+    levels::{
+        CameraZone, CameraZoneMode, CheckpointData, DashPickup, LevelData, LevelElement,
+        LevelGenerator, Levels, SpikeData, SpikeDir, PLATFORM_THICKNESS, SPIKE_SIZE,
+    },
+    Checkpoint, GameState, InLevel, PhysicsPause, PhysicsPauseReason, Spike,
+};
+
+const GRID_SIZE: f32 = 10.;
+const CAMERA_PAN_SPEED: f32 = 600.;
+const ZOOM_STEP: f32 = 0.1;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.;
+/// How far outside of an element's bounds a click still selects it. Platforms are only a few
+/// units thick, which would make them hard to click otherwise.
+const PICK_TOLERANCE: f32 = 4.;
+const DEFAULT_PLATFORM_SIZE: f32 = 200.;
+const DEFAULT_SLIDER_DISTANCE: f32 = 300.;
+const DEFAULT_SLIDER_SPEED: f32 = 250.;
+const DEFAULT_SPIKE_GROUP_LENGTH: f32 = 96.;
+const DEFAULT_CAMERA_ZONE_SIZE: (f32, f32) = (300., 200.);
+const DEFAULT_CAMERA_ZONE_ZOOM: f32 = 1.5;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_computed_state::<InEditor>()
+            .init_resource::<EditorInteraction>()
+            .add_systems(OnEnter(InEditor), setup_editor)
+            .add_systems(OnExit(InEditor), cleanup_editor)
+            .add_systems(OnExit(InLevel), remove_playtest)
+            .add_systems(
+                Update,
+                (
+                    load_editor_level,
+                    editor_camera,
+                    edit_with_mouse,
+                    edit_with_keyboard,
+                    switch_editor_level,
+                    rebuild_editor_level,
+                    reveal_spikes,
+                    draw_editor_gizmos,
+                    update_editor_hud,
+                )
+                    .chain()
+                    .run_if(in_state(InEditor)),
+            )
+            .add_systems(Update, open_editor.run_if(in_state(InLevel)));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InEditor;
+
+impl ComputedStates for InEditor {
+    type SourceStates = GameState;
+
+    fn compute(sources: Self::SourceStates) -> Option<Self> {
+        match sources {
+            GameState::Editor { .. } => Some(Self),
+            _ => None,
+        }
+    }
+}
+
+/// Where the player spawns when a level is started with "play from here" in the editor.
+#[derive(Resource)]
+pub struct PlaytestSpawn(pub Vec2);
+
+/// The unsaved editor version of the level that is being play tested. [`Levels`] returns it in
+/// place of the level asset, so the edits don't leak into normal play.
+#[derive(Resource)]
+pub struct PlaytestLevel {
+    pub index: u16,
+    pub data: LevelData,
+}
+
+/// The level that is currently being edited. This is kept around while play testing, so that
+/// unsaved changes survive a round trip into the level.
+#[derive(Resource)]
+struct EditorLevel {
+    index: u16,
+    handle: Option<Handle<LevelData>>,
+    data: LevelData,
+    unsaved: bool,
+    needs_rebuild: bool,
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+enum EditorTool {
+    #[default]
+    Select,
+    Platform,
+    SliderPlatform,
+    Spike,
+    SpikeGroup,
+    Checkpoint,
+    Ending,
+    DashPickup,
+    CameraZone,
+}
+
+impl EditorTool {
+    const KEYS: [(KeyCode, EditorTool); 9] = [
+        (KeyCode::Digit1, EditorTool::Select),
+        (KeyCode::Digit2, EditorTool::Platform),
+        (KeyCode::Digit3, EditorTool::SliderPlatform),
+        (KeyCode::Digit4, EditorTool::Spike),
+        (KeyCode::Digit5, EditorTool::SpikeGroup),
+        (KeyCode::Digit6, EditorTool::Checkpoint),
+        (KeyCode::Digit7, EditorTool::Ending),
+        (KeyCode::Digit8, EditorTool::DashPickup),
+        (KeyCode::Digit9, EditorTool::CameraZone),
+    ];
+
+    fn new_element(self, pos: Vec2, dir: SpikeDir) -> Option<LevelElement> {
+        let pos = (pos.x, pos.y);
+        Some(match self {
+            EditorTool::Select => return None,
+            EditorTool::Platform => LevelElement::Platform {
+                pos,
+                size: DEFAULT_PLATFORM_SIZE,
+            },
+            EditorTool::SliderPlatform => LevelElement::SliderPlatform {
+                a: pos,
+                b: (pos.0 + DEFAULT_SLIDER_DISTANCE, pos.1),
+                size: DEFAULT_PLATFORM_SIZE,
+                speed: DEFAULT_SLIDER_SPEED,
+            },
+            EditorTool::Spike => LevelElement::Spike { pos, dir },
+            EditorTool::SpikeGroup => {
+                let (start, coord) = match dir {
+                    SpikeDir::Up | SpikeDir::Down => pos,
+                    SpikeDir::Left | SpikeDir::Right => (pos.1, pos.0),
+                };
+                LevelElement::SpikeGroup {
+                    start,
+                    end: start + DEFAULT_SPIKE_GROUP_LENGTH,
+                    coord,
+                    dir,
+                }
+            }
+            EditorTool::Checkpoint => LevelElement::Checkpoint { pos },
+            EditorTool::Ending => LevelElement::Ending { pos },
+            EditorTool::DashPickup => LevelElement::DashPickup { pos },
+            // the mode can be changed in the level file
+            EditorTool::CameraZone => LevelElement::CameraZone {
+                pos,
+                size: DEFAULT_CAMERA_ZONE_SIZE,
+                mode: CameraZoneMode::Zoom(DEFAULT_CAMERA_ZONE_ZOOM),
+            },
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DragKind {
+    Move,
+    Resize,
+    MoveSliderEnd,
+}
+
+struct Drag {
+    kind: DragKind,
+    start: Vec2,
+    original: LevelElement,
+}
+
+#[derive(Resource)]
+struct EditorInteraction {
+    tool: EditorTool,
+    spike_dir: SpikeDir,
+    snap_to_grid: bool,
+    selected: Option<usize>,
+    drag: Option<Drag>,
+}
+
+impl Default for EditorInteraction {
+    fn default() -> Self {
+        Self {
+            tool: EditorTool::default(),
+            spike_dir: SpikeDir::default(),
+            snap_to_grid: true,
+            selected: None,
+            drag: None,
+        }
+    }
+}
+
+impl EditorInteraction {
+    fn snap(&self, value: f32) -> f32 {
+        if self.snap_to_grid {
+            (value / GRID_SIZE).round() * GRID_SIZE
+        } else {
+            value
+        }
+    }
+
+    fn snap_pos(&self, pos: (f32, f32)) -> (f32, f32) {
+        (self.snap(pos.0), self.snap(pos.1))
+    }
+}
+
+#[derive(Component)]
+struct EditorRoot;
+
+#[derive(Component)]
+struct EditorHud;
+
+fn setup_editor(mut commands: Commands, mut physics_pause: ResMut<PhysicsPause>) {
+    physics_pause.pause(PhysicsPauseReason::Editor);
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.,
+                color: WHITE.into(),
+                ..default()
+            },
+        )
+        .with_style(Style {
+            margin: UiRect::all(Val::Px(20.)),
+            ..default()
+        }),
+        EditorHud,
+    ));
+}
+
+#[allow(clippy::type_complexity)]
+fn cleanup_editor(
+    mut commands: Commands,
+    entities: Query<
+        Entity,
+        Or<(
+            With<EditorRoot>,
+            With<EditorHud>,
+            With<Spike>,
+            With<Checkpoint>,
+            With<DashPickup>,
+            With<CameraZone>,
+        )>,
+    >,
+    mut projections: Query<&mut OrthographicProjection, With<Camera2d>>,
+    mut physics_pause: ResMut<PhysicsPause>,
+    mut interaction: ResMut<EditorInteraction>,
+) {
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+    for mut projection in &mut projections {
+        projection.scale = 1.;
+    }
+    physics_pause.unpause(PhysicsPauseReason::Editor);
+    interaction.selected = None;
+    interaction.drag = None;
+}
+
+fn remove_playtest(mut commands: Commands) {
+    commands.remove_resource::<PlaytestSpawn>();
+    commands.remove_resource::<PlaytestLevel>();
+}
+
+/// Opens the editor for the level that is currently being played.
+fn open_editor(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    game_state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::F2) {
+        return;
+    }
+
+    let GameState::Level { index, .. } = **game_state else {
+        return;
+    };
+    next_state.set(GameState::Editor { index });
+}
+
+fn load_editor_level(
+    mut commands: Commands,
+    game_state: Res<State<GameState>>,
+    editor_level: Option<ResMut<EditorLevel>>,
+    levels: Levels,
+) {
+    if !game_state.is_changed() {
+        return;
+    }
+    let GameState::Editor { index } = **game_state else {
+        return;
+    };
+
+    if let Some(mut editor_level) = editor_level {
+        if editor_level.index == index && editor_level.unsaved {
+            editor_level.needs_rebuild = true;
+            return;
+        }
+        if editor_level.unsaved {
+            warn!(
+                "Discarding unsaved changes to level {}",
+                editor_level.index + 1
+            );
+        }
+    }
+
+    commands.insert_resource(EditorLevel {
+        index,
+        handle: levels.handle(index),
+        data: levels.get(index).cloned().unwrap_or_default(),
+        unsaved: false,
+        needs_rebuild: true,
+    });
+}
+
+fn cursor_world_position(
+    windows: &Query<&Window, With<PrimaryWindow>>,
+    cameras: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let cursor = windows.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = cameras.get_single().ok()?;
+    camera.viewport_to_world_2d(camera_transform, cursor)
+}
+
+fn editor_camera(
+    time: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+) {
+    let Ok((mut transform, mut projection)) = cameras.get_single_mut() else {
+        return;
+    };
+
+    for event in mouse_wheel.read() {
+        projection.scale =
+            (projection.scale * (1. - event.y.signum() * ZOOM_STEP)).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    let mut pan = Vec2::ZERO;
+    if mouse_input.pressed(MouseButton::Middle) {
+        for event in mouse_motion.read() {
+            pan += Vec2::new(-event.delta.x, event.delta.y) * projection.scale;
+        }
+    } else {
+        mouse_motion.clear();
+    }
+
+    // Ctrl+S saves, so don't pan while Ctrl is held
+    if !keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        let left = keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]);
+        let right = keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]);
+        let down = keyboard_input.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]);
+        let up = keyboard_input.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]);
+        let direction = Vec2::new(
+            (right as i8 - left as i8) as f32,
+            (up as i8 - down as i8) as f32,
+        );
+        pan += direction * CAMERA_PAN_SPEED * projection.scale * time.delta_seconds();
+    }
+
+    transform.translation += pan.extend(0.);
+}
+
+/// Returns the index of the topmost element at `point`, and whether the point is on the end
+/// position of a slider platform.
+fn element_at(level: &LevelData, point: Vec2) -> Option<(usize, bool)> {
+    let is_zone = |element: &LevelElement| matches!(element, LevelElement::CameraZone { .. });
+    let elements = level.elements.iter().enumerate().rev();
+    // camera zones cover other elements, so they're only picked if nothing else is there
+    elements
+        .clone()
+        .filter(|(_, element)| !is_zone(element))
+        .chain(elements.filter(|(_, element)| is_zone(element)))
+        .find_map(|(i, element)| {
+            if let Some(end) = slider_end_bounds(element) {
+                if end.inflate(PICK_TOLERANCE).contains(point) {
+                    return Some((i, true));
+                }
+            }
+
+            element
+                .bounds()
+                .iter()
+                .any(|bounds| bounds.inflate(PICK_TOLERANCE).contains(point))
+                .then_some((i, false))
+        })
+}
+
+fn slider_end_bounds(element: &LevelElement) -> Option<Rect> {
+    let LevelElement::SliderPlatform { b, size, .. } = *element else {
+        return None;
+    };
+    Some(Rect::from_center_size(
+        Vec2::new(b.0 + size / 2., b.1),
+        Vec2::new(size, PLATFORM_THICKNESS),
+    ))
+}
+
+fn translated(
+    element: &LevelElement,
+    delta: Vec2,
+    interaction: &EditorInteraction,
+) -> LevelElement {
+    let translate = |pos: (f32, f32)| interaction.snap_pos((pos.0 + delta.x, pos.1 + delta.y));
+    match *element {
+        LevelElement::Platform { pos, size } => LevelElement::Platform {
+            pos: translate(pos),
+            size,
+        },
+        LevelElement::SliderPlatform { a, b, size, speed } => {
+            let new_a = translate(a);
+            LevelElement::SliderPlatform {
+                a: new_a,
+                b: (b.0 + new_a.0 - a.0, b.1 + new_a.1 - a.1),
+                size,
+                speed,
+            }
+        }
+        LevelElement::Spike { pos, dir } => LevelElement::Spike {
+            pos: translate(pos),
+            dir,
+        },
+        LevelElement::SpikeGroup {
+            start,
+            end,
+            coord,
+            dir,
+        } => {
+            let (along, across) = match dir {
+                SpikeDir::Up | SpikeDir::Down => (delta.x, delta.y),
+                SpikeDir::Left | SpikeDir::Right => (delta.y, delta.x),
+            };
+            let new_start = interaction.snap(start + along);
+            LevelElement::SpikeGroup {
+                start: new_start,
+                end: end + new_start - start,
+                coord: interaction.snap(coord + across),
+                dir,
+            }
+        }
+        LevelElement::Checkpoint { pos } => LevelElement::Checkpoint {
+            pos: translate(pos),
+        },
+        LevelElement::Ending { pos } => LevelElement::Ending {
+            pos: translate(pos),
+        },
+        LevelElement::DashPickup { pos } => LevelElement::DashPickup {
+            pos: translate(pos),
+        },
+        LevelElement::CameraZone { pos, size, mode } => {
+            let new_pos = translate(pos);
+            let moved = |p: (f32, f32)| (p.0 + new_pos.0 - pos.0, p.1 + new_pos.1 - pos.1);
+            // the framing moves along with the zone
+            let mode = match mode {
+                CameraZoneMode::Fixed { center, zoom } => CameraZoneMode::Fixed {
+                    center: moved(center),
+                    zoom,
+                },
+                CameraZoneMode::Pan { target, hold } => CameraZoneMode::Pan {
+                    target: moved(target),
+                    hold,
+                },
+                CameraZoneMode::Zoom(zoom) => CameraZoneMode::Zoom(zoom),
+            };
+            LevelElement::CameraZone {
+                pos: new_pos,
+                size,
+                mode,
+            }
+        }
+    }
+}
+
+fn resized(element: &LevelElement, delta: Vec2, interaction: &EditorInteraction) -> LevelElement {
+    let mut element = element.clone();
+    match &mut element {
+        LevelElement::Platform { size, .. } | LevelElement::SliderPlatform { size, .. } => {
+            *size = interaction.snap(*size + delta.x).max(GRID_SIZE);
+        }
+        LevelElement::SpikeGroup {
+            start, end, dir, ..
+        } => {
+            let along = match dir {
+                SpikeDir::Up | SpikeDir::Down => delta.x,
+                SpikeDir::Left | SpikeDir::Right => delta.y,
+            };
+            *end = interaction.snap(*end + along).max(*start + SPIKE_SIZE.x);
+        }
+        LevelElement::CameraZone { size, .. } => {
+            size.0 = interaction.snap(size.0 + delta.x).max(GRID_SIZE);
+            size.1 = interaction.snap(size.1 + delta.y).max(GRID_SIZE);
+        }
+        LevelElement::Spike { .. }
+        | LevelElement::Checkpoint { .. }
+        | LevelElement::Ending { .. }
+        | LevelElement::DashPickup { .. } => {}
+    }
+    element
+}
+
+fn with_moved_slider_end(
+    element: &LevelElement,
+    delta: Vec2,
+    interaction: &EditorInteraction,
+) -> LevelElement {
+    let mut element = element.clone();
+    if let LevelElement::SliderPlatform { b, .. } = &mut element {
+        *b = interaction.snap_pos((b.0 + delta.x, b.1 + delta.y));
+    }
+    element
+}
+
+fn rotated(dir: SpikeDir) -> SpikeDir {
+    match dir {
+        SpikeDir::Up => SpikeDir::Right,
+        SpikeDir::Right => SpikeDir::Down,
+        SpikeDir::Down => SpikeDir::Left,
+        SpikeDir::Left => SpikeDir::Up,
+    }
+}
+
+fn edit_with_mouse(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    editor_level: Option<ResMut<EditorLevel>>,
+    mut interaction: ResMut<EditorInteraction>,
+) {
+    let Some(mut editor_level) = editor_level else {
+        return;
+    };
+    let Some(cursor) = cursor_world_position(&windows, &cameras) else {
+        return;
+    };
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        if let Some((index, on_slider_end)) = element_at(&editor_level.data, cursor) {
+            let kind = if on_slider_end {
+                DragKind::MoveSliderEnd
+            } else if keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+                DragKind::Resize
+            } else {
+                DragKind::Move
+            };
+            interaction.selected = Some(index);
+            interaction.drag = Some(Drag {
+                kind,
+                start: cursor,
+                original: editor_level.data.elements[index].clone(),
+            });
+        } else if let Some(element) = interaction.tool.new_element(
+            Vec2::new(interaction.snap(cursor.x), interaction.snap(cursor.y)),
+            interaction.spike_dir,
+        ) {
+            let elements = &mut editor_level.data.elements;
+            // there can only be one door per level
+            if matches!(element, LevelElement::Ending { .. }) {
+                elements.retain(|e| !matches!(e, LevelElement::Ending { .. }));
+            }
+            elements.push(element);
+            interaction.selected = Some(elements.len() - 1);
+            editor_level.unsaved = true;
+            editor_level.needs_rebuild = true;
+        } else {
+            interaction.selected = None;
+        }
+    }
+
+    if mouse_input.pressed(MouseButton::Left) {
+        if let (Some(drag), Some(selected)) = (&interaction.drag, interaction.selected) {
+            let delta = cursor - drag.start;
+            let element = match drag.kind {
+                DragKind::Move => translated(&drag.original, delta, &interaction),
+                DragKind::Resize => resized(&drag.original, delta, &interaction),
+                DragKind::MoveSliderEnd => {
+                    with_moved_slider_end(&drag.original, delta, &interaction)
+                }
+            };
+
+            if element != editor_level.data.elements[selected] {
+                editor_level.data.elements[selected] = element;
+                editor_level.unsaved = true;
+                editor_level.needs_rebuild = true;
+            }
+        }
+    }
+
+    if mouse_input.just_released(MouseButton::Left) {
+        interaction.drag = None;
+    }
+
+    if mouse_input.just_pressed(MouseButton::Right) {
+        if let Some((index, _)) = element_at(&editor_level.data, cursor) {
+            editor_level.data.elements.remove(index);
+            interaction.selected = None;
+            interaction.drag = None;
+            editor_level.unsaved = true;
+            editor_level.needs_rebuild = true;
+        }
+    }
+}
+
+fn edit_with_keyboard(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    editor_level: Option<ResMut<EditorLevel>>,
+    mut interaction: ResMut<EditorInteraction>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    cameras: Query<&Transform, With<Camera2d>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(mut editor_level) = editor_level else {
+        return;
+    };
+
+    for (key, tool) in EditorTool::KEYS {
+        if keyboard_input.just_pressed(key) {
+            interaction.tool = tool;
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyG) {
+        interaction.snap_to_grid = !interaction.snap_to_grid;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyR) {
+        let selected = interaction
+            .selected
+            .and_then(|i| editor_level.data.elements.get_mut(i));
+        match selected {
+            Some(LevelElement::Spike { dir, .. } | LevelElement::SpikeGroup { dir, .. }) => {
+                *dir = rotated(*dir);
+                editor_level.unsaved = true;
+                editor_level.needs_rebuild = true;
+            }
+            _ => interaction.spike_dir = rotated(interaction.spike_dir),
+        }
+    }
+
+    if keyboard_input.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        if let Some(selected) = interaction.selected.take() {
+            editor_level.data.elements.remove(selected);
+            interaction.drag = None;
+            editor_level.unsaved = true;
+            editor_level.needs_rebuild = true;
+        }
+    }
+
+    let ctrl = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if ctrl && keyboard_input.just_pressed(KeyCode::KeyS) {
+        match save_level(&editor_level, &asset_server) {
+            Ok(path) => {
+                info!("Saved level {} to {}", editor_level.index + 1, path);
+                editor_level.unsaved = false;
+            }
+            Err(err) => error!("Failed to save level {}: {err}", editor_level.index + 1),
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        // play test with the current state of the editor, even if it hasn't been saved yet
+        if editor_level.handle.is_none() {
+            warn!(
+                "Level {} is not part of the level manifest",
+                editor_level.index + 1
+            );
+            return;
+        }
+        commands.insert_resource(PlaytestLevel {
+            index: editor_level.index,
+            data: editor_level.data.clone(),
+        });
+
+        if let Ok(camera) = cameras.get_single() {
+            let spawn = camera.translation.truncate();
+            commands.insert_resource(PlaytestSpawn(Vec2::new(
+                interaction.snap(spawn.x),
+                interaction.snap(spawn.y),
+            )));
+        }
+        next_state.set(GameState::level(editor_level.index));
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn switch_editor_level(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    editor_level: Option<Res<EditorLevel>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    levels: Levels,
+) {
+    let Some(editor_level) = editor_level else {
+        return;
+    };
+
+    if keyboard_input.just_pressed(KeyCode::PageUp) && editor_level.index > 0 {
+        next_state.set(GameState::Editor {
+            index: editor_level.index - 1,
+        });
+    }
+    if keyboard_input.just_pressed(KeyCode::PageDown) && editor_level.index + 1 < levels.count() {
+        next_state.set(GameState::Editor {
+            index: editor_level.index + 1,
+        });
+    }
+}
+
+fn save_level(editor_level: &EditorLevel, asset_server: &AssetServer) -> Result<String, String> {
+    let asset_path = editor_level
+        .handle
+        .as_ref()
+        .and_then(|handle| asset_server.get_path(handle.id()))
+        .ok_or("the level was not loaded from a file")?;
+    let path = FileAssetReader::get_base_path()
+        .join("assets")
+        .join(asset_path.path());
+
+    let contents =
+        ron::ser::to_string_pretty(&editor_level.data, PrettyConfig::new().depth_limit(2))
+            .map_err(|err| err.to_string())?;
+    std::fs::write(&path, contents).map_err(|err| err.to_string())?;
+    Ok(path.display().to_string())
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn rebuild_editor_level(
+    editor_level: Option<ResMut<EditorLevel>>,
+    entities: Query<
+        Entity,
+        Or<(
+            With<EditorRoot>,
+            With<Spike>,
+            With<Checkpoint>,
+            With<DashPickup>,
+            With<CameraZone>,
+        )>,
+    >,
+    // see setup_level_content
+    mut commands: Commands,
+    commands2: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    spike_data: ResMut<SpikeData>,
+    checkpoint_data: ResMut<CheckpointData>,
+) {
+    let Some(mut editor_level) = editor_level else {
+        return;
+    };
+    if !editor_level.needs_rebuild {
+        return;
+    }
+    editor_level.needs_rebuild = false;
+
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let root = commands.spawn((
+        EditorRoot,
+        TransformBundle::default(),
+        VisibilityBundle::default(),
+    ));
+    LevelGenerator::setup_level(
+        commands2,
+        root,
+        &mut meshes,
+        &mut materials,
+        spike_data,
+        checkpoint_data,
+        editor_level.index,
+        &editor_level.data,
+    );
+}
+
+/// Spikes are invisible until the player dies to them, but the editor should show everything.
+fn reveal_spikes(mut spikes: Query<&mut Visibility, Added<Spike>>) {
+    for mut visibility in &mut spikes {
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn draw_editor_gizmos(
+    mut gizmos: Gizmos,
+    editor_level: Option<Res<EditorLevel>>,
+    interaction: Res<EditorInteraction>,
+    cameras: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+) {
+    let Some(editor_level) = editor_level else {
+        return;
+    };
+
+    if let Ok((transform, projection)) = cameras.get_single() {
+        let center = transform.translation.truncate();
+        let area = Rect::from_center_size(center, projection.area.size());
+        let spacing = GRID_SIZE * 5.;
+        let color = GRAY.with_alpha(0.15);
+
+        let mut x = (area.min.x / spacing).floor() * spacing;
+        while x <= area.max.x {
+            gizmos.line_2d(Vec2::new(x, area.min.y), Vec2::new(x, area.max.y), color);
+            x += spacing;
+        }
+        let mut y = (area.min.y / spacing).floor() * spacing;
+        while y <= area.max.y {
+            gizmos.line_2d(Vec2::new(area.min.x, y), Vec2::new(area.max.x, y), color);
+            y += spacing;
+        }
+    }
+
+    // player spawn
+    gizmos.circle_2d(Vec2::ZERO, 10., ORANGE);
+
+    for (i, element) in editor_level.data.elements.iter().enumerate() {
+        let selected = interaction.selected == Some(i);
+
+        if let (LevelElement::SliderPlatform { a, size, .. }, Some(end)) =
+            (element, slider_end_bounds(element))
+        {
+            gizmos.line_2d(Vec2::new(a.0 + size / 2., a.1), end.center(), SKY_BLUE);
+            gizmos.rect_2d(end.center(), 0., end.size(), SKY_BLUE);
+        }
+
+        // camera zones are invisible in the level
+        if let LevelElement::CameraZone { pos, size, mode } = *element {
+            let center = Vec2::new(pos.0, pos.1);
+            gizmos.rect_2d(center, 0., Vec2::new(size.0, size.1), VIOLET);
+            match mode {
+                CameraZoneMode::Fixed { center: target, .. }
+                | CameraZoneMode::Pan { target, .. } => {
+                    let target = Vec2::new(target.0, target.1);
+                    gizmos.line_2d(center, target, VIOLET);
+                    gizmos.circle_2d(target, 6., VIOLET);
+                }
+                CameraZoneMode::Zoom(_) => {}
+            }
+        }
+
+        if selected {
+            for bounds in element.bounds() {
+                gizmos.rect_2d(
+                    bounds.center(),
+                    0.,
+                    bounds.inflate(PICK_TOLERANCE).size(),
+                    YELLOW,
+                );
+            }
+        }
+    }
+}
+
+fn update_editor_hud(
+    editor_level: Option<Res<EditorLevel>>,
+    interaction: Res<EditorInteraction>,
+    mut hud: Query<&mut Text, With<EditorHud>>,
+) {
+    let Some(editor_level) = editor_level else {
+        return;
+    };
+    let Ok(mut text) = hud.get_single_mut() else {
+        return;
+    };
+
+    text.sections[0].value = format!(
+        "Editing level {}{}\n\
+         Tool: {:?} (1-9)   Spike direction: {:?} (R)   Snap: {} (G)\n\
+         LMB: place / drag, Shift+LMB: resize, RMB / Del: delete\n\
+         WASD / MMB: pan, scroll: zoom, PgUp / PgDn: switch level\n\
+         Ctrl+S: save, F2: play from here, Esc: exit",
+        editor_level.index + 1,
+        if editor_level.unsaved { "*" } else { "" },
+        interaction.tool,
+        interaction.spike_dir,
+        if interaction.snap_to_grid {
+            "on"
+        } else {
+            "off"
+        },
+    );
+}
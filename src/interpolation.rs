@@ -0,0 +1,75 @@
+use bevy::{app::RunFixedMainLoop, prelude::*, time::run_fixed_main_schedule};
+
+/// Smooths the rendered movement of entities that are moved in the fixed timestep by
+/// interpolating between the last two fixed timestep positions.
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Interpolated>()
+            .add_systems(FixedFirst, restore_physical_translation)
+            .add_systems(
+                FixedLast,
+                (init_physical_translation, record_physical_translation),
+            )
+            .add_systems(
+                RunFixedMainLoop,
+                interpolate_translation.after(run_fixed_main_schedule),
+            );
+    }
+}
+
+/// Marks an entity whose translation should be interpolated.
+#[derive(Default, Component, Reflect)]
+#[reflect(Component)]
+pub struct Interpolated;
+
+/// The translation of an [`Interpolated`] entity after the last two fixed timesteps.
+///
+/// Remove this component after teleporting an entity so it doesn't visibly slide to its new
+/// position. It is added back automatically.
+#[derive(Component)]
+pub struct PhysicalTranslation {
+    previous: Vec3,
+    current: Vec3,
+}
+
+/// Outside of the fixed timestep, the transform holds the interpolated translation, so the
+/// actual one has to be put back before gameplay and physics run.
+fn restore_physical_translation(mut query: Query<(&mut Transform, &PhysicalTranslation)>) {
+    for (mut transform, physical_translation) in &mut query {
+        transform.translation = physical_translation.current;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn init_physical_translation(
+    mut commands: Commands,
+    query: Query<(Entity, &Transform), (With<Interpolated>, Without<PhysicalTranslation>)>,
+) {
+    for (entity, transform) in &query {
+        commands.entity(entity).insert(PhysicalTranslation {
+            previous: transform.translation,
+            current: transform.translation,
+        });
+    }
+}
+
+fn record_physical_translation(mut query: Query<(&Transform, &mut PhysicalTranslation)>) {
+    for (transform, mut physical_translation) in &mut query {
+        physical_translation.previous = physical_translation.current;
+        physical_translation.current = transform.translation;
+    }
+}
+
+fn interpolate_translation(
+    time: Res<Time<Fixed>>,
+    mut query: Query<(&mut Transform, &PhysicalTranslation)>,
+) {
+    let t = time.overstep_fraction();
+    for (mut transform, physical_translation) in &mut query {
+        transform.translation = physical_translation
+            .previous
+            .lerp(physical_translation.current, t);
+    }
+}
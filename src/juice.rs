@@ -0,0 +1,219 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::{color::palettes::css::*, prelude::*, transform::TransformSystem};
+
+use crate::{
+    death_condition, settings::Settings, DeathEvent, InLevel, IsPaused, PhysicsPause,
+    PhysicsPauseReason,
+};
+
+/// Camera shake, hit-stop and a screen fade when the player dies.
+pub struct JuicePlugin;
+
+impl Plugin for JuicePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<JuiceConfig>()
+            .init_resource::<CameraShake>()
+            .init_resource::<HitStop>()
+            .add_systems(PreUpdate, remove_camera_shake)
+            .add_systems(Update, (start_death_effects, update_death_fade).chain())
+            // the hit-stop counts fixed timesteps, so it lasts just as long in replays
+            .add_systems(
+                FixedUpdate,
+                (start_hit_stop, update_hit_stop)
+                    .chain()
+                    .after(death_condition)
+                    .run_if(in_state(IsPaused::Unpaused)),
+            )
+            .add_systems(
+                PostUpdate,
+                apply_camera_shake.before(TransformSystem::TransformPropagate),
+            )
+            .add_systems(OnExit(InLevel), cleanup_death_effects);
+    }
+}
+
+/// Tuning for the death effects and respawning.
+#[derive(Resource)]
+pub struct JuiceConfig {
+    /// Shake added when the player dies, from 0 to 1
+    pub death_trauma: f32,
+    /// How much trauma goes away per second
+    pub trauma_decay: f32,
+    /// How far the camera moves at full trauma, in world units
+    pub max_shake_offset: f32,
+    /// in Hz
+    pub shake_frequency: f32,
+    /// How long the physics freeze for
+    pub hit_stop: Duration,
+    pub fade: Duration,
+    /// Opacity of the fade at its start
+    pub fade_alpha: f32,
+    /// How long the player stays dead before respawning, unless instant respawn is enabled
+    pub respawn_delay: Duration,
+}
+
+impl Default for JuiceConfig {
+    fn default() -> Self {
+        Self {
+            death_trauma: 0.6,
+            trauma_decay: 1.5,
+            max_shake_offset: 20.,
+            shake_frequency: 15.,
+            hit_stop: Duration::from_millis(100),
+            fade: Duration::from_millis(400),
+            fade_alpha: 0.6,
+            respawn_delay: Duration::from_millis(800),
+        }
+    }
+}
+
+/// Shakes the camera with the square of the trauma, which goes down over time.
+#[derive(Resource, Default)]
+struct CameraShake {
+    trauma: f32,
+    /// The offset that was added to the camera this frame
+    offset: Vec2,
+}
+
+#[derive(Resource, Default)]
+struct HitStop(Option<Timer>);
+
+#[derive(Component)]
+struct DeathFade(Timer);
+
+fn start_death_effects(
+    mut commands: Commands,
+    mut death_event_reader: EventReader<DeathEvent>,
+    config: Res<JuiceConfig>,
+    settings: Res<Settings>,
+    mut camera_shake: ResMut<CameraShake>,
+    fades: Query<Entity, With<DeathFade>>,
+) {
+    if death_event_reader.read().count() == 0 {
+        return;
+    }
+
+    if settings.screen_shake {
+        camera_shake.trauma = (camera_shake.trauma + config.death_trauma).min(1.);
+    }
+
+    for entity in &fades {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            background_color: BLACK.with_alpha(config.fade_alpha).into(),
+            ..default()
+        },
+        DeathFade(Timer::new(config.fade, TimerMode::Once)),
+    ));
+}
+
+fn start_hit_stop(
+    mut death_event_reader: EventReader<DeathEvent>,
+    config: Res<JuiceConfig>,
+    mut hit_stop: ResMut<HitStop>,
+    mut physics_pause: ResMut<PhysicsPause>,
+) {
+    if death_event_reader.read().count() == 0 || config.hit_stop.is_zero() {
+        return;
+    }
+
+    physics_pause.pause(PhysicsPauseReason::HitStop);
+    hit_stop.0 = Some(Timer::new(config.hit_stop, TimerMode::Once));
+}
+
+fn update_hit_stop(
+    time: Res<Time>,
+    mut hit_stop: ResMut<HitStop>,
+    mut physics_pause: ResMut<PhysicsPause>,
+) {
+    let Some(timer) = &mut hit_stop.0 else {
+        return;
+    };
+    if !timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    hit_stop.0 = None;
+    physics_pause.unpause(PhysicsPauseReason::HitStop);
+}
+
+fn update_death_fade(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    config: Res<JuiceConfig>,
+    mut fades: Query<(Entity, &mut DeathFade, &mut BackgroundColor)>,
+) {
+    for (entity, mut fade, mut bg) in &mut fades {
+        if fade.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        *bg = BLACK
+            .with_alpha(config.fade_alpha * fade.0.fraction_remaining())
+            .into();
+    }
+}
+
+fn remove_camera_shake(
+    mut camera_shake: ResMut<CameraShake>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    if camera_shake.offset == Vec2::ZERO {
+        return;
+    }
+
+    for mut camera in &mut cameras {
+        camera.translation -= camera_shake.offset.extend(0.);
+    }
+    camera_shake.offset = Vec2::ZERO;
+}
+
+fn apply_camera_shake(
+    time: Res<Time<Real>>,
+    config: Res<JuiceConfig>,
+    mut camera_shake: ResMut<CameraShake>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    if camera_shake.trauma <= 0. {
+        return;
+    }
+
+    camera_shake.trauma =
+        (camera_shake.trauma - config.trauma_decay * time.delta_seconds()).max(0.);
+    // overlapping sine waves look random enough and don't need an rng
+    let t = time.elapsed_seconds() * config.shake_frequency * TAU;
+    let noise = Vec2::new(
+        0.6 * t.sin() + 0.4 * (2.3 * t + 1.1).sin(),
+        0.6 * (1.3 * t + 0.7).sin() + 0.4 * (2.9 * t + 2.3).sin(),
+    );
+    let offset = noise * config.max_shake_offset * camera_shake.trauma.powi(2);
+
+    for mut camera in &mut cameras {
+        camera.translation += offset.extend(0.);
+    }
+    camera_shake.offset = offset;
+}
+
+fn cleanup_death_effects(
+    mut commands: Commands,
+    mut camera_shake: ResMut<CameraShake>,
+    mut hit_stop: ResMut<HitStop>,
+    mut physics_pause: ResMut<PhysicsPause>,
+    fades: Query<Entity, With<DeathFade>>,
+) {
+    camera_shake.trauma = 0.;
+    hit_stop.0 = None;
+    physics_pause.unpause(PhysicsPauseReason::HitStop);
+    for entity in &fades {
+        commands.entity(entity).despawn_recursive();
+    }
+}
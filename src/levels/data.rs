@@ -0,0 +1,386 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::{
+        io::Reader, AssetLoader, AsyncReadExt, LoadContext, LoadState, RecursiveDependencyLoadState,
+    },
+    ecs::system::SystemParam,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{
+    spike_group_positions, SpikeDir, DOOR_SIZE, PICKUP_SIZE, PLATFORM_THICKNESS, SPIKE_SIZE,
+};
+use crate::{
+    character_controller::{DEFAULT_COYOTE_TIME, DEFAULT_JUMP_BUFFER},
+    editor::PlaytestLevel,
+    GameState, BOTTOM_WORLD_BOUNDARY,
+};
+
+const LEVEL_MANIFEST: &str = "levels/levels.manifest.ron";
+
+pub struct LevelDataPlugin;
+
+impl Plugin for LevelDataPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LevelData>()
+            .init_asset::<LevelManifest>()
+            .init_asset_loader::<LevelDataLoader>()
+            .init_asset_loader::<LevelManifestLoader>()
+            .add_systems(Startup, load_level_manifest)
+            .add_systems(
+                Update,
+                wait_for_level_manifest.run_if(in_state(GameState::Loading)),
+            );
+    }
+}
+
+/// A single level, as described by a `.level.ron` file.
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelData {
+    /// Path of the music track, the default track is played if this isn't set.
+    #[serde(default)]
+    pub music: Option<String>,
+    #[serde(default)]
+    pub controller: ControllerSettings,
+    #[serde(default)]
+    pub camera: CameraSettings,
+    #[serde(default)]
+    pub elements: Vec<LevelElement>,
+}
+
+/// Per-level tuning of the player's character controller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControllerSettings {
+    /// in seconds
+    pub coyote_time: f32,
+    /// in seconds
+    pub jump_buffer: f32,
+    /// Enables wall sliding and wall jumping
+    pub wall_jump: bool,
+}
+
+impl Default for ControllerSettings {
+    fn default() -> Self {
+        Self {
+            coyote_time: DEFAULT_COYOTE_TIME.as_secs_f32(),
+            jump_buffer: DEFAULT_JUMP_BUFFER.as_secs_f32(),
+            wall_jump: false,
+        }
+    }
+}
+
+impl ControllerSettings {
+    pub fn coyote_time(&self) -> Duration {
+        Duration::from_secs_f32(self.coyote_time.max(0.))
+    }
+
+    pub fn jump_buffer(&self) -> Duration {
+        Duration::from_secs_f32(self.jump_buffer.max(0.))
+    }
+}
+
+/// How the camera follows the player in a level. Distances are in world units.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// The area the camera stays inside of. Without bounds, the camera only stays above the
+    /// bottom of the world.
+    pub bounds: Option<Rect>,
+    /// How far the camera looks ahead of the player when moving at full speed
+    pub look_ahead: f32,
+    /// How far the player can move up or down from the center before the camera follows
+    pub dead_zone: f32,
+    /// Roughly the time in seconds the camera takes to catch up with the player, 0 snaps to it
+    pub smoothing: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            bounds: None,
+            look_ahead: 80.,
+            dead_zone: 50.,
+            smoothing: 0.15,
+        }
+    }
+}
+
+impl CameraSettings {
+    pub fn bounds(&self) -> Rect {
+        self.bounds.unwrap_or(Rect {
+            min: Vec2::new(f32::NEG_INFINITY, BOTTOM_WORLD_BOUNDARY),
+            max: Vec2::INFINITY,
+        })
+    }
+}
+
+/// Positions are in world units. For platforms, `pos` is the left edge of the platform.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LevelElement {
+    Platform {
+        pos: (f32, f32),
+        size: f32,
+    },
+    /// speed: u/s
+    SliderPlatform {
+        a: (f32, f32),
+        b: (f32, f32),
+        size: f32,
+        speed: f32,
+    },
+    Spike {
+        pos: (f32, f32),
+        #[serde(default)]
+        dir: SpikeDir,
+    },
+    /// Spikes from `start` to `end` along the axis the spikes are lined up on, at `coord` on the
+    /// other axis.
+    SpikeGroup {
+        start: f32,
+        end: f32,
+        coord: f32,
+        dir: SpikeDir,
+    },
+    Checkpoint {
+        pos: (f32, f32),
+    },
+    Ending {
+        pos: (f32, f32),
+    },
+    /// Awards the dash ability until the level is restarted
+    DashPickup {
+        pos: (f32, f32),
+    },
+    /// Changes what the camera does while the player is inside of it. `pos` is the center.
+    CameraZone {
+        pos: (f32, f32),
+        size: (f32, f32),
+        mode: CameraZoneMode,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CameraZoneMode {
+    /// Locks the camera to `center`. A `zoom` above 1 shows more of the level.
+    Fixed { center: (f32, f32), zoom: f32 },
+    /// Keeps following the player with a different zoom
+    Zoom(f32),
+    /// Pans over to `target` the first time the player enters, stays there for `hold` seconds
+    /// and goes back to following the player
+    Pan { target: (f32, f32), hold: f32 },
+}
+
+impl LevelData {
+    /// Whether `rect` overlaps anything the player can collide with or die from.
+    pub fn overlaps_solid(&self, rect: Rect) -> bool {
+        self.elements
+            .iter()
+            .filter(|element| {
+                !matches!(
+                    element,
+                    LevelElement::Checkpoint { .. }
+                        | LevelElement::Ending { .. }
+                        | LevelElement::DashPickup { .. }
+                        | LevelElement::CameraZone { .. }
+                )
+            })
+            .flat_map(LevelElement::bounds)
+            .any(|bounds| !bounds.intersect(rect).is_empty())
+    }
+}
+
+impl LevelElement {
+    /// The collider bounds of this element. Slider platforms report their starting position
+    /// and spike groups report one rectangle per spike.
+    pub fn bounds(&self) -> Vec<Rect> {
+        match *self {
+            LevelElement::Platform { pos, size }
+            | LevelElement::SliderPlatform { a: pos, size, .. } => vec![Rect::from_center_size(
+                Vec2::new(pos.0 + size / 2., pos.1),
+                Vec2::new(size, PLATFORM_THICKNESS),
+            )],
+            LevelElement::Spike { pos, dir } => vec![spike_bounds(pos, dir)],
+            LevelElement::SpikeGroup {
+                start,
+                end,
+                coord,
+                dir,
+            } => spike_group_positions(start, end, coord, dir)
+                .into_iter()
+                .map(|pos| spike_bounds(pos, dir))
+                .collect(),
+            LevelElement::Checkpoint { pos } => {
+                vec![Rect::new(pos.0 - 20., pos.1, pos.0 + 20., pos.1 + 40.)]
+            }
+            LevelElement::Ending { pos } => vec![Rect::from_center_size(
+                Vec2::new(pos.0, pos.1 + DOOR_SIZE.y / 2.),
+                DOOR_SIZE,
+            )],
+            LevelElement::DashPickup { pos } => {
+                vec![Rect::from_center_size(Vec2::new(pos.0, pos.1), PICKUP_SIZE)]
+            }
+            LevelElement::CameraZone { pos, size, .. } => vec![Rect::from_center_size(
+                Vec2::new(pos.0, pos.1),
+                Vec2::new(size.0, size.1),
+            )],
+        }
+    }
+}
+
+fn spike_bounds(pos: (f32, f32), dir: SpikeDir) -> Rect {
+    let (x_off, y_off, _) = dir.offset_and_rotation();
+    Rect::from_center_size(Vec2::new(pos.0 + x_off, pos.1 + y_off), SPIKE_SIZE)
+}
+
+/// The ordered list of levels in the game.
+#[derive(Asset, TypePath, Debug)]
+pub struct LevelManifest {
+    #[dependency]
+    pub levels: Vec<Handle<LevelData>>,
+}
+
+#[derive(Deserialize)]
+struct LevelManifestFile {
+    /// Paths relative to the assets folder
+    levels: Vec<String>,
+}
+
+#[derive(Resource)]
+pub struct LevelManifestHandle(pub Handle<LevelManifest>);
+
+#[derive(Debug, Error)]
+pub enum LevelLoaderError {
+    #[error("could not read level file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse level file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+struct LevelDataLoader;
+
+impl AssetLoader for LevelDataLoader {
+    type Asset = LevelData;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+#[derive(Default)]
+struct LevelManifestLoader;
+
+impl AssetLoader for LevelManifestLoader {
+    type Asset = LevelManifest;
+    type Settings = ();
+    type Error = LevelLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file: LevelManifestFile = ron::de::from_bytes(&bytes)?;
+
+        Ok(LevelManifest {
+            levels: file
+                .levels
+                .into_iter()
+                .map(|path| load_context.load(path))
+                .collect(),
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["manifest.ron"]
+    }
+}
+
+/// Read access to the levels listed in the level manifest.
+#[derive(SystemParam)]
+pub struct Levels<'w> {
+    manifest_handle: Res<'w, LevelManifestHandle>,
+    manifests: Res<'w, Assets<LevelManifest>>,
+    levels: Res<'w, Assets<LevelData>>,
+    playtest: Option<Res<'w, PlaytestLevel>>,
+}
+
+impl<'w> Levels<'w> {
+    fn manifest(&self) -> Option<&LevelManifest> {
+        self.manifests.get(&self.manifest_handle.0)
+    }
+
+    pub fn count(&self) -> u16 {
+        self.manifest().map(|m| m.levels.len() as u16).unwrap_or(0)
+    }
+
+    /// Returns the index of the level with the given asset id.
+    pub fn index_of(&self, id: AssetId<LevelData>) -> Option<u16> {
+        self.manifest()?
+            .levels
+            .iter()
+            .position(|handle| handle.id() == id)
+            .map(|index| index as u16)
+    }
+
+    pub fn handle(&self, index: u16) -> Option<Handle<LevelData>> {
+        self.manifest()?.levels.get(index as usize).cloned()
+    }
+
+    /// Returns the editor's version of the level while it is being play tested.
+    pub fn get(&self, index: u16) -> Option<&LevelData> {
+        if let Some(playtest) = self.playtest.as_ref().filter(|p| p.index == index) {
+            return Some(&playtest.data);
+        }
+        let handle = self.manifest()?.levels.get(index as usize)?;
+        self.levels.get(handle)
+    }
+}
+
+fn load_level_manifest(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(LevelManifestHandle(asset_server.load(LEVEL_MANIFEST)));
+}
+
+/// Quits the game if the levels can't be loaded, since there's nothing to play without them.
+fn wait_for_level_manifest(
+    asset_server: Res<AssetServer>,
+    manifest_handle: Res<LevelManifestHandle>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut exit_writer: EventWriter<AppExit>,
+) {
+    if asset_server.is_loaded_with_dependencies(&manifest_handle.0) {
+        next_state.set(GameState::MainMenu);
+        return;
+    }
+
+    if let Some(LoadState::Failed(err)) = asset_server.get_load_state(&manifest_handle.0) {
+        error!("Failed to load level manifest: {err}");
+    } else if let Some(RecursiveDependencyLoadState::Failed) =
+        asset_server.get_recursive_dependency_load_state(&manifest_handle.0)
+    {
+        error!("Failed to load one or more levels listed in the level manifest");
+    } else {
+        return;
+    }
+    exit_writer.send(AppExit::error());
+}
@@ -7,25 +7,37 @@ use bevy::{
     prelude::*,
     sprite::{Anchor, MaterialMesh2dBundle, Mesh2dHandle},
 };
-use level0::Level0;
-use level1::Level1;
+use serde::{Deserialize, Serialize};
 
-mod level0;
-mod level1;
+use crate::interpolation::Interpolated;
+
+pub use data::{CameraZoneMode, LevelData, LevelDataPlugin, LevelElement, Levels};
+
+mod data;
 
 const PLATFORM_Z: f32 = 10.;
 const SPIKE_Z: f32 = 5.;
+const PICKUP_Z: f32 = 5.;
 const DOOR_Z: f32 = -1.;
 const LEVEL_TEXT_Z: f32 = -10.;
-const SPIKE_SIZE: Vec2 = Vec2::new(24., 24.);
-const PLATFORM_THICKNESS: f32 = 4.;
+pub const SPIKE_SIZE: Vec2 = Vec2::new(24., 24.);
+pub const PLATFORM_THICKNESS: f32 = 4.;
 const DOOR_SIZE: Vec2 = Vec2::new(30., 50.);
+const PICKUP_SIZE: Vec2 = Vec2::new(16., 16.);
 
 #[derive(Reflect, Component)]
 #[reflect(Component)]
 pub struct LevelEnd;
 
-#[derive(Default, Clone, Copy)]
+/// Changes what the camera does while the player is inside of it, see [`CameraZoneMode`].
+#[derive(Component)]
+pub struct CameraZone {
+    pub mode: CameraZoneMode,
+    /// Whether a [`CameraZoneMode::Pan`] already happened since the level was reset
+    pub triggered: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SpikeDir {
     #[default]
     Up,
@@ -34,6 +46,32 @@ pub enum SpikeDir {
     Right,
 }
 
+impl SpikeDir {
+    /// Returns the offset of the spike's center from its position and its rotation.
+    fn offset_and_rotation(self) -> (f32, f32, f32) {
+        match self {
+            SpikeDir::Up => (0., 0., 0.),
+            SpikeDir::Down => (0., SPIKE_SIZE.y / 2., PI),
+            SpikeDir::Left => (SPIKE_SIZE.x / 2., SPIKE_SIZE.y / 2., FRAC_PI_2),
+            SpikeDir::Right => (-SPIKE_SIZE.x / 2., SPIKE_SIZE.y / 2., -FRAC_PI_2),
+        }
+    }
+}
+
+/// Positions of the individual spikes in a spike group
+fn spike_group_positions(start: f32, end: f32, coord2: f32, dir: SpikeDir) -> Vec<(f32, f32)> {
+    let mut positions = Vec::new();
+    let mut coord1 = ((end - start) % SPIKE_SIZE.x) / 2. + start;
+    while coord1 <= end {
+        positions.push(match dir {
+            SpikeDir::Up | SpikeDir::Down => (coord1, coord2),
+            SpikeDir::Left | SpikeDir::Right => (coord2, coord1),
+        });
+        coord1 += SPIKE_SIZE.x;
+    }
+    positions
+}
+
 #[derive(Default, Component)]
 pub struct Spike {
     pub group: Option<usize>,
@@ -44,6 +82,10 @@ pub struct Checkpoint {
     pub active: bool,
 }
 
+/// Gives the player the dash ability when collected
+#[derive(Component)]
+pub struct DashPickup;
+
 #[derive(Clone, Debug, PartialEq, Reflect, Component)]
 #[reflect(Debug, Component, PartialEq)]
 pub struct PersistentColliderConstructor(ColliderConstructor);
@@ -95,7 +137,7 @@ pub fn persistent_anchor_system(
             continue;
         }
 
-        commands.entity(entity).insert(persistent_anchor.0.clone());
+        commands.entity(entity).insert(persistent_anchor.0);
     }
 }
 
@@ -173,6 +215,7 @@ impl PlatformBundle {
 struct MovingPlatformBundle {
     ty: MovingPlatformType,
     platform: MovingPlatform,
+    interpolated: Interpolated,
 }
 
 impl MovingPlatformBundle {
@@ -180,6 +223,7 @@ impl MovingPlatformBundle {
         Self {
             ty: MovingPlatformType::slider(a, b, speed),
             platform: MovingPlatform::default(),
+            interpolated: Interpolated,
         }
     }
 }
@@ -258,22 +302,6 @@ impl CheckpointData {
     }
 }
 
-macro_rules! level_generator {
-    ($name:ident, $lower_name:ident, $func:expr) => {
-        pub(super) trait $name {
-            fn $lower_name(&mut self);
-        }
-
-        impl<'a> $name for LevelGenerator<'a> {
-            fn $lower_name(&mut self) {
-                $func(self)
-            }
-        }
-    };
-}
-
-use level_generator;
-
 pub struct LevelGenerator<'a> {
     commands: Commands<'a, 'a>,
     level_commands: EntityCommands<'a>,
@@ -304,6 +332,7 @@ impl<'a> LevelGenerator<'a> {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn setup_level(
         commands: Commands<'a, 'a>,
         level_commands: EntityCommands<'a>,
@@ -312,6 +341,7 @@ impl<'a> LevelGenerator<'a> {
         spike_data: ResMut<'a, SpikeData>,
         checkpoint_data: ResMut<'a, CheckpointData>,
         idx: u16,
+        level: &LevelData,
     ) {
         let mut lg = Self::new(
             commands,
@@ -322,13 +352,10 @@ impl<'a> LevelGenerator<'a> {
             checkpoint_data,
         );
         lg.spawn_level_text(idx);
-        match idx {
-            0 => lg.level0(),
-            1 => lg.level1(),
-            _ => panic!("Invalid level index: {idx}"),
-        }
+        lg.spawn_elements(level);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn setup_level_without_permanent_entities(
         commands: Commands<'a, 'a>,
         level_commands: EntityCommands<'a>,
@@ -337,6 +364,7 @@ impl<'a> LevelGenerator<'a> {
         spike_data: ResMut<'a, SpikeData>,
         checkpoint_data: ResMut<'a, CheckpointData>,
         idx: u16,
+        level: &LevelData,
     ) {
         let mut lg = Self::new(
             commands,
@@ -348,21 +376,35 @@ impl<'a> LevelGenerator<'a> {
         );
         lg.spawn_level_text(idx);
         lg.set_enable_permanent_entities(false);
-        match idx {
-            0 => lg.level0(),
-            1 => lg.level1(),
-            _ => panic!("Invalid level index: {idx}"),
-        }
-    }
-
-    pub fn level_count() -> u16 {
-        2
+        lg.spawn_elements(level);
     }
 
     fn set_enable_permanent_entities(&mut self, enable: bool) {
         self.enable_permanent_entities = enable;
     }
 
+    fn spawn_elements(&mut self, level: &LevelData) {
+        for element in &level.elements {
+            match *element {
+                LevelElement::Platform { pos, size } => self.platform(pos, size),
+                LevelElement::SliderPlatform { a, b, size, speed } => {
+                    self.slider_platform(a, b, size, speed)
+                }
+                LevelElement::Spike { pos, dir } => self.spike_dir(pos, dir),
+                LevelElement::SpikeGroup {
+                    start,
+                    end,
+                    coord,
+                    dir,
+                } => self.spike_group(start, end, coord, dir),
+                LevelElement::Checkpoint { pos } => self.checkpoint(pos),
+                LevelElement::Ending { pos } => self.ending(pos),
+                LevelElement::DashPickup { pos } => self.dash_pickup(pos),
+                LevelElement::CameraZone { pos, size, mode } => self.camera_zone(pos, size, mode),
+            }
+        }
+    }
+
     fn spawn_level_text(&mut self, index: u16) {
         let bundle = Text2dBundle {
             text: Text::from_section(
@@ -379,7 +421,7 @@ impl<'a> LevelGenerator<'a> {
 
         let id = self
             .commands
-            .spawn((PersistentAnchor(bundle.text_anchor.clone()), bundle))
+            .spawn((PersistentAnchor(bundle.text_anchor), bundle))
             .id();
         self.level_commands.add_child(id);
     }
@@ -405,13 +447,8 @@ impl<'a> LevelGenerator<'a> {
         self.level_commands.add_child(id);
     }
 
-    fn spike_base(&mut self, pos: (f32, f32), dir: SpikeDir) -> EntityCommands {
-        let (x_off, y_off, rot) = match dir {
-            SpikeDir::Up => (0., 0., 0.),
-            SpikeDir::Down => (0., SPIKE_SIZE.y / 2., PI),
-            SpikeDir::Left => (SPIKE_SIZE.x / 2., SPIKE_SIZE.y / 2., FRAC_PI_2),
-            SpikeDir::Right => (-SPIKE_SIZE.x / 2., SPIKE_SIZE.y / 2., -FRAC_PI_2),
-        };
+    fn spike_base(&mut self, pos: (f32, f32), dir: SpikeDir) -> EntityCommands<'_> {
+        let (x_off, y_off, rot) = dir.offset_and_rotation();
 
         self.commands.spawn((
             MaterialMesh2dBundle {
@@ -429,10 +466,6 @@ impl<'a> LevelGenerator<'a> {
         ))
     }
 
-    fn spike(&mut self, pos: (f32, f32)) {
-        self.spike_dir(pos, SpikeDir::default());
-    }
-
     fn spike_dir(&mut self, pos: (f32, f32), dir: SpikeDir) {
         if !self.enable_permanent_entities {
             return;
@@ -445,17 +478,10 @@ impl<'a> LevelGenerator<'a> {
             return;
         }
 
-        let mut coord1 = ((end - start) % SPIKE_SIZE.x) / 2. + start;
         let group = self.current_spike_group;
-        while coord1 <= end {
-            let pos = match dir {
-                SpikeDir::Up | SpikeDir::Down => (coord1, coord2),
-                SpikeDir::Left | SpikeDir::Right => (coord2, coord1),
-            };
-
+        for pos in spike_group_positions(start, end, coord2, dir) {
             self.spike_base(pos, dir)
                 .insert(Spike { group: Some(group) });
-            coord1 += SPIKE_SIZE.x;
         }
 
         self.current_spike_group += 1;
@@ -477,6 +503,41 @@ impl<'a> LevelGenerator<'a> {
         ));
     }
 
+    fn dash_pickup(&mut self, pos: (f32, f32)) {
+        if !self.enable_permanent_entities {
+            return;
+        }
+        self.commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::from(DEEP_SKY_BLUE),
+                    custom_size: Some(PICKUP_SIZE),
+                    ..default()
+                },
+                transform: Transform::from_xyz(pos.0, pos.1, PICKUP_Z)
+                    .with_rotation(Quat::from_rotation_z(PI / 4.)),
+                ..default()
+            },
+            Collider::rectangle(PICKUP_SIZE.x, PICKUP_SIZE.y),
+            DashPickup,
+        ));
+    }
+
+    fn camera_zone(&mut self, pos: (f32, f32), size: (f32, f32), mode: CameraZoneMode) {
+        if !self.enable_permanent_entities {
+            return;
+        }
+        self.commands.spawn((
+            TransformBundle::from_transform(Transform::from_xyz(pos.0, pos.1, 0.)),
+            Collider::rectangle(size.0, size.1),
+            Sensor,
+            CameraZone {
+                mode,
+                triggered: false,
+            },
+        ));
+    }
+
     fn ending(&mut self, pos: (f32, f32)) {
         let id = self
             .commands
@@ -1,36 +1,82 @@
+use std::time::Duration;
+
 use avian2d::{math::Vector, prelude::*};
 use bevy::{
-    audio::{PlaybackMode, Volume},
     color::palettes::css::*,
-    ecs::system::EntityCommands,
     prelude::*,
     text::{Text2dBounds, TextLayoutInfo},
     time::Stopwatch,
+    utils::HashSet,
+};
+use serde::{Deserialize, Serialize};
+
+use actions::{Action, ActionState, ActionsPlugin};
+use character_controller::{
+    CharacterControllerBundle, CharacterControllerPlugin, CoyoteTime, DashBundle, JumpBuffer,
+    MaxSpeed, PlayerInputSet, TouchingWall, WallJump,
 };
-use character_controller::{CharacterControllerBundle, CharacterControllerPlugin};
+use editor::{EditorPlugin, PlaytestLevel, PlaytestSpawn};
+use interpolation::{Interpolated, InterpolationPlugin, PhysicalTranslation};
+use juice::{JuiceConfig, JuicePlugin};
 use levels::{
-    persistent_anchor_system, persistent_collider_constructor_system, Checkpoint, CheckpointData,
-    LevelEnd, LevelGenerator, MovingPlatform, MovingPlatformType, PersistentAnchor,
-    PersistentColliderConstructor, Spike, SpikeData,
+    persistent_anchor_system, persistent_collider_constructor_system, CameraZone, CameraZoneMode,
+    Checkpoint, CheckpointData, DashPickup, LevelData, LevelDataPlugin, LevelEnd, LevelGenerator,
+    Levels, MovingPlatform, MovingPlatformType, PersistentAnchor, PersistentColliderConstructor,
+    Spike, SpikeData,
+};
+use particles::ParticlesPlugin;
+use profile::{LevelProgress, Profile, ProfilePlugin};
+use replay::{ReplayPlayback, ReplayPlugin};
+use settings::{Settings, SettingsPlugin};
+use sound::{CurrentMusic, Music, PlaySfx, Sfx, SoundPlugin};
+use ui::{
+    game_complete_menu::GameCompleteMenuPlugin, level_complete_menu::LevelCompleteMenuPlugin,
+    main_menu::MainMenuPlugin, pause_menu::PauseMenuPlugin, spawn_text, SubMenu, UiPlugin,
 };
-use ui::{main_menu::MainMenuPlugin, pause_menu::PauseMenuPlugin, UiPlugin};
 
+mod actions;
 mod character_controller;
+mod editor;
+mod interpolation;
+mod juice;
 mod levels;
+mod particles;
+mod persistence;
+mod profile;
+mod replay;
+mod settings;
+mod sound;
 mod ui;
 
 const PLAYER_SIZE: Vec2 = Vec2::new(20., 40.);
 const BOTTOM_WORLD_BOUNDARY: f32 = -500.;
-const BACKGROUND_AUDIO: &str = "background.ogg";
-const CHECKPOINT_ACTIVATE_SOUND_EFFECT: &str = "checkpoint_activate.ogg";
-const DEATH_SOUND_EFFECT: &str = "player_death.ogg";
-const LEVEL_COMPLETE_SOUND_EFFECT: &str = "level_complete.ogg";
+/// How far the player can overlap a solid and still keep their position on hot reload
+const PLAYER_POSITION_TOLERANCE: f32 = 1.;
+const MENU_MUSIC: &str = "background.ogg";
+/// Played in levels that don't specify their own music
+const DEFAULT_LEVEL_MUSIC: &str = "background.ogg";
+/// Gameplay and physics ticks per second
+const DEFAULT_TICK_RATE: f64 = 60.;
+/// Overrides [`DEFAULT_TICK_RATE`]
+const TICK_RATE_ENV: &str = "RAGE_PLATFORMER_TICK_RATE";
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, States)]
 pub enum GameState {
+    /// Waiting for the level manifest and all levels to be loaded
+    Loading,
     MainMenu,
     LevelSelect,
-    Level { index: u16, paused: bool },
+    Level {
+        index: u16,
+        paused: bool,
+    },
+    Editor {
+        index: u16,
+    },
+    /// Showing the results of the level that was just completed, see [`LevelResults`]
+    LevelComplete,
+    /// Credits and overall stats after the last level was completed
+    GameComplete,
 }
 
 impl GameState {
@@ -74,6 +120,16 @@ impl ComputedStates for IsPaused {
     }
 }
 
+/// Whether the player is playing or waiting to respawn after dying.
+#[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[source(InLevel = InLevel)]
+enum PlayerState {
+    #[default]
+    Alive,
+    /// The player is frozen and hidden until the [`RespawnTimer`] finishes
+    Dying,
+}
+
 #[derive(Event)]
 struct LevelCompleteEvent;
 
@@ -84,14 +140,71 @@ enum LevelRestartEvent {
     /// We need to add the level id since the state changes aren't committed in the same frame,
     /// meaning setup_level_content doesn't get the correct index directly.
     FullReset(u16),
+    /// Same as [`LevelRestartEvent::FullReset`], but keeps the player where they are if that
+    /// position is still valid in the reloaded level.
+    HotReload(u16),
+}
+
+/// A restart the player asked for. Unlike [`LevelRestartEvent`]s, these are part of replays.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum RestartRequest {
+    /// Go back to the last checkpoint
+    LastSave,
+    /// Start the level over, including the time and deaths
+    Level,
 }
 
 #[derive(Default, Resource)]
 struct LevelStopwatch(Stopwatch);
 
+/// Where a [`CameraZoneMode::Pan`] moved the camera to and how long it stays there.
+#[derive(Default, Resource)]
+struct CameraPan(Option<(Vec2, Timer)>);
+
 #[derive(Default, Resource)]
 struct DeathCounter(usize);
 
+#[derive(Resource)]
+struct RespawnTimer(Timer);
+
+/// Why the physics are paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicsPauseReason {
+    Menu,
+    HitStop,
+    Editor,
+}
+
+/// The physics only run while there's no reason to pause them. Systems add and remove their
+/// reason instead of pausing [`Time<Physics>`] themselves, so they don't unpause each other.
+#[derive(Resource, Default)]
+pub struct PhysicsPause(HashSet<PhysicsPauseReason>);
+
+impl PhysicsPause {
+    pub fn pause(&mut self, reason: PhysicsPauseReason) {
+        self.0.insert(reason);
+    }
+
+    pub fn unpause(&mut self, reason: PhysicsPauseReason) {
+        self.0.remove(&reason);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        !self.0.is_empty()
+    }
+}
+
+/// How the player did in the last completed level
+#[derive(Resource)]
+struct LevelResults {
+    index: u16,
+    /// in seconds
+    time: f32,
+    deaths: usize,
+    /// The player's progress before completing the level this time
+    previous_progress: Option<LevelProgress>,
+}
+
 #[derive(Resource)]
 struct SaveData {
     scene: Handle<DynamicScene>,
@@ -101,6 +214,8 @@ struct SaveData {
 #[derive(Default, Event)]
 struct DeathEvent {
     spike: Option<Entity>,
+    /// Where the player died
+    position: Vec2,
 }
 
 #[derive(Event)]
@@ -129,9 +244,6 @@ struct TimeText;
 #[derive(Component)]
 struct DeathsText;
 
-#[derive(Component)]
-struct BackgroundAudio;
-
 fn main() {
     App::new()
         .register_type::<PersistentColliderConstructor>()
@@ -146,14 +258,26 @@ fn main() {
         .add_plugins((
             DefaultPlugins,
             // 1 meter = 20 pixels
-            PhysicsPlugins::default().with_length_unit(20.),
+            PhysicsPlugins::new(FixedPostUpdate).with_length_unit(20.),
+            InterpolationPlugin,
+            ActionsPlugin,
             CharacterControllerPlugin,
+            LevelDataPlugin,
+            EditorPlugin,
+            ProfilePlugin,
+            SettingsPlugin,
+            SoundPlugin,
             UiPlugin,
             MainMenuPlugin,
             PauseMenuPlugin,
+            LevelCompleteMenuPlugin,
+            GameCompleteMenuPlugin,
         ))
+        // a plugin tuple can't have more than 15 elements
+        .add_plugins((JuicePlugin, ParticlesPlugin, ReplayPlugin))
         .add_event::<LevelCompleteEvent>()
         .add_event::<LevelRestartEvent>()
+        .add_event::<RestartRequest>()
         .add_event::<DeathEvent>()
         .add_event::<CheckpointSaveEvent>()
         .add_event::<RemoveSaveEvent>()
@@ -162,13 +286,21 @@ fn main() {
         .insert_resource(CheckpointData::default())
         .insert_resource(DeathCounter::default())
         .init_resource::<LevelStopwatch>()
+        .init_resource::<PhysicsPause>()
         .add_computed_state::<InLevel>()
         .add_computed_state::<IsPaused>()
-        .insert_state(GameState::MainMenu)
-        .add_systems(Startup, setup)
+        .add_sub_state::<PlayerState>()
+        .insert_state(GameState::Loading)
+        .add_systems(Startup, (configure_tick_rate, setup))
+        .add_systems(Update, select_music)
         .add_systems(OnEnter(InLevel), setup_level)
         .add_systems(OnEnter(IsPaused::Paused), begin_pause)
         .add_systems(OnExit(IsPaused::Paused), end_pause)
+        // right before the physics step, so a hit-stop freezes the timestep it starts in
+        .add_systems(
+            FixedPostUpdate,
+            apply_physics_pause.before(PhysicsSet::StepSimulation),
+        )
         .add_systems(
             OnExit(InLevel),
             (cleanup_level, cleanup_level_content, remove_save),
@@ -177,18 +309,30 @@ fn main() {
             Update,
             (
                 camera_smooth_follow_player,
+                restart_input
+                    .in_set(PlayerInputSet)
+                    .run_if(in_state(PlayerState::Alive)),
+            )
+                .run_if(in_state(IsPaused::Unpaused)),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                tick_level_stopwatch,
                 moving_platform_system,
+                (checkpoint_system, create_save.pipe(store_save)).chain(),
+                dash_pickup_system,
                 (
-                    checkpoint_system,
-                    create_save.pipe(store_save),
-                    checkpoint_load,
-                )
-                    .chain(),
-                (
-                    level_complete_condition,
-                    on_level_completed,
-                    death_condition,
+                    restart_level,
+                    (
+                        level_complete_condition,
+                        on_level_completed,
+                        death_condition,
+                    )
+                        .run_if(player_alive),
                     update_spike_visibility,
+                    start_death_sequence,
+                    respawn_after_delay.run_if(not(player_alive)),
                     setup_level_content,
                 )
                     .chain(),
@@ -206,17 +350,45 @@ fn main() {
             )
                 .run_if(in_state(IsPaused::Unpaused)),
         )
-        .add_systems(Update, pause_system.run_if(in_state(InLevel)))
+        .add_systems(
+            Update,
+            (
+                pause_system.run_if(in_state(SubMenu::None)),
+                reload_modified_level,
+            )
+                .run_if(in_state(InLevel)),
+        )
         .run();
 }
 
+fn configure_tick_rate(
+    mut fixed_time: ResMut<Time<Fixed>>,
+    mut physics_time: ResMut<Time<Physics>>,
+) {
+    let tick_rate = match std::env::var_os(TICK_RATE_ENV) {
+        None => DEFAULT_TICK_RATE,
+        Some(value) => match value.to_str().and_then(|value| value.parse::<f64>().ok()) {
+            Some(tick_rate) if tick_rate.is_finite() && tick_rate > 0. => tick_rate,
+            _ => {
+                warn!("Invalid {TICK_RATE_ENV} {value:?}, using {DEFAULT_TICK_RATE}");
+                DEFAULT_TICK_RATE
+            }
+        },
+    };
+
+    fixed_time.set_timestep_hz(tick_rate);
+    // step the physics exactly once per fixed timestep
+    physics_time.set_timestep_mode(TimestepMode::FixedOnce {
+        delta: Duration::from_secs_f64(1. / tick_rate),
+    });
+}
+
 fn setup(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
 }
 
 fn setup_level(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
     game_state: Res<State<GameState>>,
     mut level_changed_writer: EventWriter<LevelRestartEvent>,
 ) {
@@ -230,6 +402,7 @@ fn setup_level(
             ..default()
         },
         Player,
+        Interpolated,
         CharacterControllerBundle::new(Collider::capsule(10., 20.)),
         Friction::ZERO.with_combine_rule(CoefficientCombine::Min),
         Restitution::ZERO.with_combine_rule(CoefficientCombine::Min),
@@ -252,22 +425,7 @@ fn setup_level(
             Hud,
         ))
         .with_children(|parent| {
-            fn text<'a>(
-                parent: &'a mut ChildBuilder,
-                text: impl Into<String>,
-                font_size: f32,
-            ) -> EntityCommands<'a> {
-                parent.spawn(TextBundle::from_section(
-                    text,
-                    TextStyle {
-                        font_size,
-                        color: WHITE.into(),
-                        ..default()
-                    },
-                ))
-            }
-
-            text(parent, "Level 1", 50.).insert(LevelText);
+            spawn_text(parent, "Level 1", 50.).insert(LevelText);
 
             parent.spawn(NodeBundle {
                 style: Style {
@@ -280,19 +438,13 @@ fn setup_level(
                 ..default()
             });
 
-            text(parent, "Time: 12.1s", 25.).insert(TimeText);
-            text(parent, "Deaths: 0", 25.).insert(DeathsText);
+            spawn_text(parent, "Time: 12.1s", 25.).insert(TimeText);
+            spawn_text(parent, "Deaths: 0", 25.).insert(DeathsText);
         });
 
     commands.insert_resource(LevelStopwatch::default());
-
-    commands.spawn((
-        AudioBundle {
-            source: asset_server.load(BACKGROUND_AUDIO),
-            settings: PlaybackSettings::LOOP.with_volume(Volume::new(0.2)),
-        },
-        BackgroundAudio,
-    ));
+    commands.insert_resource(CameraPan::default());
+    commands.insert_resource(DeathCounter::default());
 
     let GameState::Level { index, .. } = **game_state else {
         return;
@@ -304,14 +456,19 @@ fn cleanup_level(
     mut commands: Commands,
     player: Query<Entity, With<Player>>,
     hud: Query<Entity, With<Hud>>,
-    background_audio: Query<Entity, With<BackgroundAudio>>,
+    mut projections: Query<&mut OrthographicProjection, With<Camera2d>>,
 ) {
+    // camera zones may have zoomed in or out, and the menus expect the default zoom
+    for mut projection in &mut projections {
+        projection.scale = 1.;
+    }
+
     let Ok(player) = player.get_single() else {
         return;
     };
     commands.entity(player).despawn_recursive();
 
-    for entity in hud.iter().chain(background_audio.iter()) {
+    for entity in &hud {
         commands.entity(entity).despawn_recursive();
     }
 
@@ -322,6 +479,15 @@ fn remove_save(
     mut commands: Commands,
     save_data: Option<Res<SaveData>>,
     mut dynamic_scenes: ResMut<Assets<DynamicScene>>,
+) {
+    remove_save_data(&mut commands, save_data.as_deref(), &mut dynamic_scenes);
+}
+
+/// Removes the checkpoint save and the scene it stores.
+fn remove_save_data(
+    commands: &mut Commands,
+    save_data: Option<&SaveData>,
+    dynamic_scenes: &mut Assets<DynamicScene>,
 ) {
     if let Some(save_data) = save_data {
         dynamic_scenes.remove(&save_data.scene);
@@ -329,25 +495,37 @@ fn remove_save(
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn setup_level_content(
     mut level_restart_reader: EventReader<LevelRestartEvent>,
     level_root: Query<Entity, With<LevelRoot>>,
-    spikes: Query<Entity, With<Spike>>,
-    checkpoints: Query<Entity, With<Checkpoint>>,
-    mut player: Query<(&mut Transform, Option<&mut LinearVelocity>), With<Player>>,
+    permanent_entities: Query<
+        Entity,
+        Or<(
+            With<Spike>,
+            With<Checkpoint>,
+            With<DashPickup>,
+            With<CameraZone>,
+        )>,
+    >,
+    mut player: Query<(Entity, &mut Transform, Option<&mut LinearVelocity>), With<Player>>,
     // The EntityCommands that we get from Commands::spawn() reborrows the Commands, which means
     // we cannot borrow it again when passing it to setup_level. Therefore, we just ask Bevy to
     // give us another 'static Commands lol...
     mut commands: Commands,
     commands2: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    (mut meshes, mut materials): (ResMut<Assets<Mesh>>, ResMut<Assets<ColorMaterial>>),
     spike_data: ResMut<SpikeData>,
     checkpoint_data: ResMut<CheckpointData>,
     game_state: Res<State<GameState>>,
     save_data: Option<Res<SaveData>>,
     mut scene_spawner: ResMut<SceneSpawner>,
+    levels: Levels,
+    playtest_spawn: Option<Res<PlaytestSpawn>>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
+    let spawn_position = playtest_spawn.map_or(Vec3::ZERO, |spawn| spawn.0.extend(0.));
+
     // reset level
     let Some(level_restart_event) = level_restart_reader.read().next() else {
         return;
@@ -357,10 +535,12 @@ fn setup_level_content(
         commands.entity(level_root).despawn_recursive();
     }
 
-    let (mut player_transform, player_velocity) = player.single_mut();
+    let (player, mut player_transform, player_velocity) = player.single_mut();
     if let Some(mut vel) = player_velocity {
         vel.0 = Vector::ZERO;
     }
+    // don't interpolate between the old and the new position
+    commands.entity(player).remove::<PhysicalTranslation>();
 
     match level_restart_event {
         LevelRestartEvent::RestoreLastSave => {
@@ -374,10 +554,15 @@ fn setup_level_content(
                 player_transform.translation = save_data.position.extend(0.);
                 scene_spawner.spawn_dynamic_as_child(save_data.scene.clone_weak(), level_root.id());
             } else {
-                player_transform.translation = Vec3::ZERO;
+                player_transform.translation = spawn_position;
                 let GameState::Level { index, .. } = **game_state else {
                     return;
                 };
+                let Some(level) = levels.get(index) else {
+                    error!("Invalid level index: {index}, returning to the main menu");
+                    next_state.set(GameState::MainMenu);
+                    return;
+                };
 
                 LevelGenerator::setup_level_without_permanent_entities(
                     commands2,
@@ -387,14 +572,36 @@ fn setup_level_content(
                     spike_data,
                     checkpoint_data,
                     index,
+                    level,
                 );
             }
         }
-        LevelRestartEvent::FullReset(index) => {
-            player_transform.translation = Vec3::ZERO;
-            for entity in spikes.iter().chain(checkpoints.iter()) {
+        LevelRestartEvent::FullReset(index) | LevelRestartEvent::HotReload(index) => {
+            let Some(level) = levels.get(*index) else {
+                error!("Invalid level index: {index}, returning to the main menu");
+                next_state.set(GameState::MainMenu);
+                return;
+            };
+
+            let keep_position = matches!(level_restart_event, LevelRestartEvent::HotReload(_))
+                && is_valid_player_position(level, player_transform.translation.truncate());
+            if !keep_position {
+                player_transform.translation = spawn_position;
+            }
+            for entity in &permanent_entities {
                 commands.entity(entity).despawn_recursive();
             }
+            // abilities from pickups only last until the level is reset
+            commands.entity(player).remove::<DashBundle>();
+            commands.entity(player).insert((
+                CoyoteTime(level.controller.coyote_time()),
+                JumpBuffer(level.controller.jump_buffer()),
+            ));
+            if level.controller.wall_jump {
+                commands.entity(player).insert(WallJump::default());
+            } else {
+                commands.entity(player).remove::<(WallJump, TouchingWall)>();
+            }
 
             let level_root = commands.spawn((
                 LevelRoot,
@@ -409,36 +616,172 @@ fn setup_level_content(
                 spike_data,
                 checkpoint_data,
                 *index,
+                level,
             );
         }
     }
 }
 
+fn is_valid_player_position(level: &LevelData, position: Vec2) -> bool {
+    // a player standing on or next to a platform can overlap it very slightly
+    let bounds = Rect::from_center_size(position, PLAYER_SIZE).inflate(-PLAYER_POSITION_TOLERANCE);
+    position.y > BOTTOM_WORLD_BOUNDARY && !level.overlaps_solid(bounds)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn reload_modified_level(
+    mut asset_events: EventReader<AssetEvent<LevelData>>,
+    levels: Levels,
+    game_state: Res<State<GameState>>,
+    mut pending_reload: Local<bool>,
+    mut level_restart_writer: EventWriter<LevelRestartEvent>,
+    mut commands: Commands,
+    save_data: Option<Res<SaveData>>,
+    mut dynamic_scenes: ResMut<Assets<DynamicScene>>,
+) {
+    let GameState::Level { index, paused } = **game_state else {
+        return;
+    };
+
+    for event in asset_events.read() {
+        if let AssetEvent::Modified { id } = event {
+            *pending_reload |= levels.index_of(*id) == Some(index);
+        }
+    }
+
+    // setup_level_content doesn't run while paused, so wait until the game is resumed
+    if !*pending_reload || paused {
+        return;
+    }
+    *pending_reload = false;
+
+    info!("Level {} was modified, reloading", index + 1);
+    level_restart_writer.send(LevelRestartEvent::HotReload(index));
+    // the saved scene was taken from the old version of the level
+    remove_save_data(&mut commands, save_data.as_deref(), &mut dynamic_scenes);
+}
+
+#[allow(clippy::type_complexity)]
 fn cleanup_level_content(
     mut commands: Commands,
     level_root: Query<Entity, With<LevelRoot>>,
-    spikes: Query<Entity, With<Spike>>,
-    checkpoints: Query<Entity, With<Checkpoint>>,
+    permanent_entities: Query<
+        Entity,
+        Or<(
+            With<Spike>,
+            With<Checkpoint>,
+            With<DashPickup>,
+            With<CameraZone>,
+        )>,
+    >,
 ) {
     if let Ok(level_root) = level_root.get_single() {
         commands.entity(level_root).despawn_recursive();
     }
 
-    for entity in spikes.iter().chain(checkpoints.iter()) {
+    for entity in &permanent_entities {
         commands.entity(entity).despawn_recursive();
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn camera_smooth_follow_player(
-    mut cameras: Query<&mut Transform, With<Camera2d>>,
-    player: Query<&Transform, (With<Player>, Without<Camera2d>)>,
+    time: Res<Time>,
+    game_state: Res<State<GameState>>,
+    levels: Levels,
+    mut camera_pan: ResMut<CameraPan>,
+    mut zones: Query<(&mut CameraZone, &CollidingEntities)>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+    player: Query<
+        (Entity, &Transform, &LinearVelocity, &MaxSpeed),
+        (With<Player>, Without<Camera2d>),
+    >,
 ) {
-    let Ok(player) = player.get_single() else {
+    let Ok((player_entity, player, velocity, max_speed)) = player.get_single() else {
+        return;
+    };
+    let GameState::Level { index, .. } = **game_state else {
         return;
     };
+    let settings = levels
+        .get(index)
+        .map(|level| level.camera.clone())
+        .unwrap_or_default();
+
+    let mut zone_mode = None;
+    for (mut zone, colliding_entities) in &mut zones {
+        if !colliding_entities.contains(&player_entity) {
+            continue;
+        }
+
+        match zone.mode {
+            CameraZoneMode::Pan { target, hold } => {
+                if !zone.triggered {
+                    zone.triggered = true;
+                    camera_pan.0 = Some((
+                        Vec2::new(target.0, target.1),
+                        Timer::from_seconds(hold.max(0.), TimerMode::Once),
+                    ));
+                }
+            }
+            mode => zone_mode = Some(mode),
+        }
+    }
+
+    if let Some((_, timer)) = &mut camera_pan.0 {
+        if timer.tick(time.delta()).finished() {
+            camera_pan.0 = None;
+        }
+    }
+    let pan_target = camera_pan.0.as_ref().map(|(target, _)| *target);
+    let (fixed_target, zoom) = match zone_mode {
+        Some(CameraZoneMode::Fixed { center, zoom }) => (Some(Vec2::new(center.0, center.1)), zoom),
+        Some(CameraZoneMode::Zoom(zoom)) => (None, zoom),
+        _ => (None, 1.),
+    };
+
+    let look_ahead = settings.look_ahead * (velocity.x / max_speed.0).clamp(-1., 1.);
+    let bounds = settings.bounds();
+    // exponential smoothing, so it doesn't depend on the frame rate
+    let t = if settings.smoothing > 0. {
+        1. - (-time.delta_seconds() / settings.smoothing).exp()
+    } else {
+        1.
+    };
+
+    for (mut camera, mut projection) in &mut cameras {
+        projection.scale += (zoom - projection.scale) * t;
+
+        // scripted framing ignores the bounds
+        if let Some(target) = pan_target.or(fixed_target) {
+            let translation = camera.translation.truncate().lerp(target, t);
+            camera.translation = translation.extend(camera.translation.z);
+            continue;
+        }
+
+        let mut target = camera.translation.truncate();
+        target.x = player.translation.x + look_ahead;
+        let offset = player.translation.y - target.y;
+        if offset.abs() > settings.dead_zone {
+            target.y = player.translation.y - settings.dead_zone.copysign(offset);
+        }
+
+        // bounds smaller than the view are centered on
+        let half_size = projection.area.half_size();
+        for axis in 0..2 {
+            let (min, max) = (
+                bounds.min[axis] + half_size[axis],
+                bounds.max[axis] - half_size[axis],
+            );
+            target[axis] = if min <= max {
+                target[axis].clamp(min, max)
+            } else {
+                (bounds.min[axis] + bounds.max[axis]) / 2.
+            };
+        }
 
-    for mut camera in &mut cameras {
-        camera.translation = camera.translation.lerp(player.translation, 0.1);
+        let translation = camera.translation.truncate().lerp(target, t);
+        camera.translation = translation.extend(camera.translation.z);
     }
 }
 
@@ -446,7 +789,12 @@ fn level_complete_condition(
     player: Query<Entity, With<Player>>,
     level_end: Query<&CollidingEntities, With<LevelEnd>>,
     mut level_complete_writer: EventWriter<LevelCompleteEvent>,
+    next_state: Res<NextState<GameState>>,
 ) {
+    // the level was already completed in an earlier fixed timestep of this frame
+    if matches!(*next_state, NextState::Pending(_)) {
+        return;
+    }
     let Ok(player) = player.get_single() else {
         return;
     };
@@ -462,17 +810,17 @@ fn level_complete_condition(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn on_level_completed(
-    mut level_stopwatch: ResMut<LevelStopwatch>,
-    mut death_counter: ResMut<DeathCounter>,
+    level_stopwatch: Res<LevelStopwatch>,
+    death_counter: Res<DeathCounter>,
+    profile: Res<Profile>,
     mut level_complete_reader: EventReader<LevelCompleteEvent>,
     game_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
-    mut level_restart_writer: EventWriter<LevelRestartEvent>,
     mut commands: Commands,
-    save_data: Option<Res<SaveData>>,
-    dynamic_scenes: ResMut<Assets<DynamicScene>>,
-    asset_server: Res<AssetServer>,
+    mut play_sfx_writer: EventWriter<PlaySfx>,
+    playtest_level: Option<Res<PlaytestLevel>>,
 ) {
     if level_complete_reader.read().count() == 0 {
         return;
@@ -481,28 +829,32 @@ fn on_level_completed(
     let GameState::Level { index, .. } = **game_state else {
         return;
     };
-    next_state.set(GameState::level(index + 1));
-    level_restart_writer.send(LevelRestartEvent::FullReset(index + 1));
-    level_stopwatch.0.reset();
-    death_counter.0 = 0;
-
-    commands.spawn(AudioBundle {
-        source: asset_server.load(LEVEL_COMPLETE_SOUND_EFFECT),
-        settings: PlaybackSettings::DESPAWN.with_volume(Volume::new(0.5)),
-    });
+    play_sfx_writer.send(PlaySfx(Sfx::LevelComplete));
+
+    // the results and the next level are about the saved level, not the edits being tested
+    if playtest_level.is_some() {
+        next_state.set(GameState::Editor { index });
+        return;
+    }
 
-    remove_save(commands, save_data, dynamic_scenes);
+    commands.insert_resource(LevelResults {
+        index,
+        time: level_stopwatch.0.elapsed_secs(),
+        deaths: death_counter.0,
+        previous_progress: profile.level(index).cloned(),
+    });
+    next_state.set(GameState::LevelComplete);
 }
 
 fn death_condition(
     player: Query<(Entity, &Transform), With<Player>>,
     mut spikes: Query<(Entity, &CollidingEntities), With<Spike>>,
     mut death_event_writer: EventWriter<DeathEvent>,
-    mut level_restart_writer: EventWriter<LevelRestartEvent>,
 ) {
     let Ok((player, player_transform)) = player.get_single() else {
         return;
     };
+    let position = player_transform.translation.truncate();
 
     for (entity, colliding_entities) in &mut spikes {
         if !colliding_entities.contains(&player) {
@@ -511,14 +863,93 @@ fn death_condition(
 
         death_event_writer.send(DeathEvent {
             spike: Some(entity),
+            position,
         });
-        level_restart_writer.send(LevelRestartEvent::RestoreLastSave);
         return;
     }
 
-    if player_transform.translation.y <= BOTTOM_WORLD_BOUNDARY {
-        death_event_writer.send(DeathEvent::default());
+    if position.y <= BOTTOM_WORLD_BOUNDARY {
+        death_event_writer.send(DeathEvent {
+            spike: None,
+            position,
+        });
+    }
+}
+
+/// Respawns the player right away with instant respawn, otherwise waits for the
+/// [`JuiceConfig::respawn_delay`].
+#[allow(clippy::too_many_arguments)]
+fn start_death_sequence(
+    mut commands: Commands,
+    mut death_event_reader: EventReader<DeathEvent>,
+    settings: Res<Settings>,
+    playback: Option<Res<ReplayPlayback>>,
+    juice_config: Res<JuiceConfig>,
+    mut player: Query<(&mut RigidBody, &mut LinearVelocity, &mut Visibility), With<Player>>,
+    mut level_restart_writer: EventWriter<LevelRestartEvent>,
+    mut next_player_state: ResMut<NextState<PlayerState>>,
+) {
+    if death_event_reader.read().count() == 0 {
+        return;
+    }
+
+    // replays have to respawn the same way they did when they were recorded
+    let instant_respawn = playback.map_or(settings.instant_respawn, |playback| {
+        playback.instant_respawn()
+    });
+    if instant_respawn || juice_config.respawn_delay.is_zero() {
         level_restart_writer.send(LevelRestartEvent::RestoreLastSave);
+        return;
+    }
+    commands.insert_resource(RespawnTimer(Timer::new(
+        juice_config.respawn_delay,
+        TimerMode::Once,
+    )));
+    next_player_state.set(PlayerState::Dying);
+
+    // keep the dead player from moving or touching anything until they respawn
+    let Ok((mut rigid_body, mut velocity, mut visibility)) = player.get_single_mut() else {
+        return;
+    };
+    *rigid_body = RigidBody::Static;
+    velocity.0 = Vector::ZERO;
+    *visibility = Visibility::Hidden;
+}
+
+fn respawn_after_delay(
+    time: Res<Time>,
+    respawn_timer: Option<ResMut<RespawnTimer>>,
+    mut player: Query<(&mut RigidBody, &mut Visibility), With<Player>>,
+    mut level_restart_writer: EventWriter<LevelRestartEvent>,
+    mut next_player_state: ResMut<NextState<PlayerState>>,
+) {
+    let Some(mut respawn_timer) = respawn_timer else {
+        return;
+    };
+    if !respawn_timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    level_restart_writer.send(LevelRestartEvent::RestoreLastSave);
+    next_player_state.set(PlayerState::Alive);
+
+    let Ok((mut rigid_body, mut visibility)) = player.get_single_mut() else {
+        return;
+    };
+    *rigid_body = RigidBody::Dynamic;
+    *visibility = Visibility::Inherited;
+}
+
+/// Whether the player is [`PlayerState::Alive`], including state changes from earlier fixed
+/// timesteps of this frame that haven't been applied yet. This way, dying and respawning take
+/// effect on the exact timestep they happen on, which keeps replays in sync.
+fn player_alive(
+    player_state: Option<Res<State<PlayerState>>>,
+    next_player_state: Option<Res<NextState<PlayerState>>>,
+) -> bool {
+    match next_player_state.as_deref() {
+        Some(NextState::Pending(state)) => *state == PlayerState::Alive,
+        _ => player_state.is_some_and(|state| **state == PlayerState::Alive),
     }
 }
 
@@ -526,7 +957,7 @@ fn update_spike_visibility(
     mut death_event_reader: EventReader<DeathEvent>,
     mut spikes: Query<(&mut Visibility, &Spike)>,
 ) {
-    for DeathEvent { spike } in death_event_reader.read() {
+    for DeathEvent { spike, .. } in death_event_reader.read() {
         let Some(spike_entity) = spike else {
             continue;
         };
@@ -553,19 +984,11 @@ fn update_death_counter(
 }
 
 fn play_death_sound_effect(
-    mut commands: Commands,
     mut death_event_reader: EventReader<DeathEvent>,
-    asset_server: Res<AssetServer>,
+    mut play_sfx_writer: EventWriter<PlaySfx>,
 ) {
     for _ in death_event_reader.read() {
-        commands.spawn(AudioBundle {
-            source: asset_server.load(DEATH_SOUND_EFFECT),
-            settings: PlaybackSettings {
-                mode: PlaybackMode::Despawn,
-                volume: Volume::new(0.3),
-                ..default()
-            },
-        });
+        play_sfx_writer.send(PlaySfx(Sfx::Death));
     }
 }
 
@@ -667,20 +1090,31 @@ fn checkpoint_system(
     }
 }
 
-fn play_checkpoint_activate_sound_effect(
+fn dash_pickup_system(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    player: Query<Entity, With<Player>>,
+    pickups: Query<(Entity, &CollidingEntities), With<DashPickup>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+
+    for (entity, colliding_entities) in &pickups {
+        if !colliding_entities.contains(&player) {
+            continue;
+        }
+
+        commands.entity(player).insert(DashBundle::default());
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn play_checkpoint_activate_sound_effect(
     mut save_event_reader: EventReader<CheckpointSaveEvent>,
+    mut play_sfx_writer: EventWriter<PlaySfx>,
 ) {
     for _ in save_event_reader.read() {
-        commands.spawn(AudioBundle {
-            source: asset_server.load(CHECKPOINT_ACTIVATE_SOUND_EFFECT),
-            settings: PlaybackSettings {
-                mode: PlaybackMode::Despawn,
-                volume: Volume::new(0.3),
-                ..default()
-            },
-        });
+        play_sfx_writer.send(PlaySfx(Sfx::CheckpointActivate));
     }
 }
 
@@ -689,16 +1123,14 @@ fn create_save(
     level_root: Query<&Children, With<LevelRoot>>,
     world: &World,
 ) -> Option<(Vec2, DynamicScene)> {
-    let Some(CheckpointSaveEvent { position }) = save_event_reader.read().next() else {
-        return None;
-    };
+    let CheckpointSaveEvent { position } = save_event_reader.read().next()?;
     let Ok(level_root_children) = level_root.get_single() else {
         return None;
     };
 
     let dynamic_scene = DynamicSceneBuilder::from_world(world)
         .deny::<Parent>()
-        .extract_entities(level_root_children.iter().map(|e| *e))
+        .extract_entities(level_root_children.iter().copied())
         .build();
 
     Some((*position, dynamic_scene))
@@ -726,21 +1158,61 @@ fn store_save(
     }
 }
 
-fn checkpoint_load(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+fn restart_input(
+    action_state: Res<ActionState>,
+    mut restart_request_writer: EventWriter<RestartRequest>,
+) {
+    if action_state.just_pressed(Action::LoadCheckpoint) {
+        restart_request_writer.send(RestartRequest::LastSave);
+    }
+    if action_state.just_pressed(Action::Restart) {
+        restart_request_writer.send(RestartRequest::Level);
+    }
+}
+
+/// Goes back to the last checkpoint or starts the current level over, including the time and
+/// deaths.
+#[allow(clippy::too_many_arguments)]
+fn restart_level(
+    mut restart_request_reader: EventReader<RestartRequest>,
+    game_state: Res<State<GameState>>,
     mut level_restart_writer: EventWriter<LevelRestartEvent>,
+    mut level_stopwatch: ResMut<LevelStopwatch>,
+    mut death_counter: ResMut<DeathCounter>,
+    mut commands: Commands,
+    save_data: Option<Res<SaveData>>,
+    mut dynamic_scenes: ResMut<Assets<DynamicScene>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::KeyL) {
-        level_restart_writer.send(LevelRestartEvent::RestoreLastSave);
+    // a full restart makes anything before it irrelevant
+    let Some(request) = restart_request_reader
+        .read()
+        .max_by_key(|request| **request == RestartRequest::Level)
+    else {
+        return;
+    };
+
+    match request {
+        RestartRequest::LastSave => {
+            level_restart_writer.send(LevelRestartEvent::RestoreLastSave);
+        }
+        RestartRequest::Level => {
+            let GameState::Level { index, .. } = **game_state else {
+                return;
+            };
+            level_restart_writer.send(LevelRestartEvent::FullReset(index));
+            level_stopwatch.0.reset();
+            death_counter.0 = 0;
+            remove_save_data(&mut commands, save_data.as_deref(), &mut dynamic_scenes);
+        }
     }
 }
 
 fn pause_system(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_state: Res<ActionState>,
     game_state: Res<State<GameState>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    if !keyboard_input.just_pressed(KeyCode::Escape) {
+    if !action_state.just_pressed(Action::Pause) {
         return;
     }
 
@@ -754,29 +1226,57 @@ fn pause_system(
     });
 }
 
-fn begin_pause(
-    mut physics_time: ResMut<Time<Physics>>,
-    mut background_audio: Query<&mut AudioSink, With<BackgroundAudio>>,
-) {
-    physics_time.pause();
-    for sink in &mut background_audio {
+fn begin_pause(mut physics_pause: ResMut<PhysicsPause>, music: Query<&AudioSink, With<Music>>) {
+    physics_pause.pause(PhysicsPauseReason::Menu);
+    for sink in &music {
         sink.pause();
     }
 }
 
-fn end_pause(
-    mut physics_time: ResMut<Time<Physics>>,
-    mut background_audio: Query<&mut AudioSink, With<BackgroundAudio>>,
-) {
-    physics_time.unpause();
-    for sink in &mut background_audio {
+fn end_pause(mut physics_pause: ResMut<PhysicsPause>, music: Query<&AudioSink, With<Music>>) {
+    physics_pause.unpause(PhysicsPauseReason::Menu);
+    for sink in &music {
         sink.play();
     }
 }
 
+fn apply_physics_pause(physics_pause: Res<PhysicsPause>, mut physics_time: ResMut<Time<Physics>>) {
+    if physics_pause.is_paused() {
+        physics_time.pause();
+    } else {
+        physics_time.unpause();
+    }
+}
+
+/// Menus play the menu music and levels their own track.
+fn select_music(
+    game_state: Res<State<GameState>>,
+    levels: Levels,
+    mut current_music: ResMut<CurrentMusic>,
+) {
+    let track = match **game_state {
+        GameState::MainMenu
+        | GameState::LevelSelect
+        | GameState::LevelComplete
+        | GameState::GameComplete => Some(MENU_MUSIC.to_string()),
+        GameState::Level { index, .. } => Some(
+            levels
+                .get(index)
+                .and_then(|level| level.music.clone())
+                .unwrap_or_else(|| DEFAULT_LEVEL_MUSIC.to_string()),
+        ),
+        GameState::Loading | GameState::Editor { .. } => None,
+    };
+    current_music.set_if_neq(CurrentMusic(track));
+}
+
+fn tick_level_stopwatch(time: Res<Time>, mut level_stopwatch: ResMut<LevelStopwatch>) {
+    level_stopwatch.0.tick(time.delta());
+}
+
+#[allow(clippy::type_complexity)]
 fn update_hud(
-    time: Res<Time<Physics>>,
-    mut level_stopwatch: ResMut<LevelStopwatch>,
+    level_stopwatch: Res<LevelStopwatch>,
     game_state: Res<State<GameState>>,
     deaths: Res<DeathCounter>,
     mut texts: Query<(&mut Text, Has<LevelText>, Has<TimeText>, Has<DeathsText>)>,
@@ -788,8 +1288,6 @@ fn update_hud(
         return;
     };
 
-    level_stopwatch.0.tick(time.delta());
-
     for (mut text, is_level_text, is_time_text, is_deaths_text) in &mut texts {
         text.sections[0].value = if is_level_text {
             format!("Level {}", level_idx + 1)
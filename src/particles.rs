@@ -0,0 +1,275 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::{color::palettes::css::*, prelude::*};
+
+use crate::{
+    character_controller::{Grounded, JumpEvent},
+    CheckpointSaveEvent, DeathEvent, InLevel, IsPaused, Player, PLAYER_SIZE,
+};
+
+/// Short-lived sprites for landing dust, jump puffs, death bursts and checkpoints. Other effects
+/// can be spawned by sending [`SpawnParticles`].
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnParticles>()
+            .init_resource::<ParticleConfig>()
+            .add_systems(
+                Update,
+                (
+                    (
+                        emit_landing_dust,
+                        emit_jump_puffs,
+                        emit_death_burst,
+                        emit_checkpoint_burst,
+                    ),
+                    spawn_particles,
+                    update_particles,
+                )
+                    .chain()
+                    .run_if(in_state(IsPaused::Unpaused)),
+            )
+            .add_systems(OnExit(InLevel), cleanup_particles);
+    }
+}
+
+#[derive(Resource)]
+pub struct ParticleConfig {
+    /// Particles that would go over this limit aren't spawned
+    pub max_particles: usize,
+}
+
+impl Default for ParticleConfig {
+    fn default() -> Self {
+        Self { max_particles: 500 }
+    }
+}
+
+/// Describes a burst of particles.
+#[derive(Debug, Clone)]
+pub struct ParticleEmitter {
+    pub count: u32,
+    pub lifetime: Duration,
+    /// The direction the particles fly in, in radians counter-clockwise from the right
+    pub direction: f32,
+    /// How far the direction of a particle can be off, in radians. [`TAU`] spreads them in all
+    /// directions.
+    pub spread: f32,
+    /// Slowest and fastest particle speed, in world units per second
+    pub speed: (f32, f32),
+    /// Downwards acceleration, in world units per second squared
+    pub gravity: f32,
+    pub size: Vec2,
+    pub start_color: Color,
+    /// The color the particles fade to over their lifetime
+    pub end_color: Color,
+}
+
+impl ParticleEmitter {
+    pub fn landing_dust() -> Self {
+        Self {
+            count: 8,
+            lifetime: Duration::from_millis(300),
+            direction: TAU / 4.,
+            spread: TAU / 2.,
+            speed: (40., 120.),
+            gravity: 200.,
+            size: Vec2::splat(4.),
+            start_color: LIGHT_GRAY.with_alpha(0.8).into(),
+            end_color: LIGHT_GRAY.with_alpha(0.).into(),
+        }
+    }
+
+    pub fn jump_puff() -> Self {
+        Self {
+            count: 6,
+            lifetime: Duration::from_millis(250),
+            direction: -TAU / 4.,
+            spread: TAU / 3.,
+            speed: (30., 90.),
+            gravity: 0.,
+            size: Vec2::splat(4.),
+            start_color: WHITE.with_alpha(0.7).into(),
+            end_color: WHITE.with_alpha(0.).into(),
+        }
+    }
+
+    pub fn death_burst(color: Color) -> Self {
+        Self {
+            count: 24,
+            lifetime: Duration::from_millis(700),
+            direction: 0.,
+            spread: TAU,
+            speed: (120., 300.),
+            gravity: 800.,
+            size: Vec2::splat(5.),
+            start_color: color,
+            end_color: color.with_alpha(0.),
+        }
+    }
+
+    pub fn checkpoint_burst() -> Self {
+        Self {
+            count: 16,
+            lifetime: Duration::from_millis(500),
+            direction: TAU / 4.,
+            spread: TAU / 2.,
+            speed: (80., 200.),
+            gravity: 400.,
+            size: Vec2::splat(4.),
+            start_color: LIGHT_GREEN.into(),
+            end_color: WHITE.with_alpha(0.).into(),
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct SpawnParticles {
+    pub emitter: ParticleEmitter,
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    gravity: f32,
+    lifetime: Timer,
+    start_color: Color,
+    end_color: Color,
+}
+
+fn emit_landing_dust(
+    player: Query<&Transform, (With<Player>, Added<Grounded>)>,
+    mut spawn_particles_writer: EventWriter<SpawnParticles>,
+) {
+    for transform in &player {
+        spawn_particles_writer.send(SpawnParticles {
+            emitter: ParticleEmitter::landing_dust(),
+            position: feet(transform),
+        });
+    }
+}
+
+fn emit_jump_puffs(
+    mut jump_event_reader: EventReader<JumpEvent>,
+    player: Query<&Transform, With<Player>>,
+    mut spawn_particles_writer: EventWriter<SpawnParticles>,
+) {
+    for JumpEvent { entity } in jump_event_reader.read() {
+        let Ok(transform) = player.get(*entity) else {
+            continue;
+        };
+        spawn_particles_writer.send(SpawnParticles {
+            emitter: ParticleEmitter::jump_puff(),
+            position: feet(transform),
+        });
+    }
+}
+
+fn emit_death_burst(
+    mut death_event_reader: EventReader<DeathEvent>,
+    player: Query<&Sprite, With<Player>>,
+    mut spawn_particles_writer: EventWriter<SpawnParticles>,
+) {
+    let color = player
+        .get_single()
+        .map_or(Color::WHITE, |sprite| sprite.color);
+    for death in death_event_reader.read() {
+        spawn_particles_writer.send(SpawnParticles {
+            emitter: ParticleEmitter::death_burst(color),
+            position: death.position,
+        });
+    }
+}
+
+fn emit_checkpoint_burst(
+    mut save_event_reader: EventReader<CheckpointSaveEvent>,
+    mut spawn_particles_writer: EventWriter<SpawnParticles>,
+) {
+    for save in save_event_reader.read() {
+        spawn_particles_writer.send(SpawnParticles {
+            emitter: ParticleEmitter::checkpoint_burst(),
+            position: save.position,
+        });
+    }
+}
+
+/// The bottom center of the player, which is where dust comes from.
+fn feet(transform: &Transform) -> Vec2 {
+    transform.translation.truncate() - Vec2::new(0., PLAYER_SIZE.y / 2. * transform.scale.y)
+}
+
+fn spawn_particles(
+    mut commands: Commands,
+    mut spawn_particles_reader: EventReader<SpawnParticles>,
+    config: Res<ParticleConfig>,
+    particles: Query<(), With<Particle>>,
+    mut seed: Local<u32>,
+) {
+    let mut particle_count = particles.iter().count();
+    for SpawnParticles { emitter, position } in spawn_particles_reader.read() {
+        let count =
+            (emitter.count as usize).min(config.max_particles.saturating_sub(particle_count));
+        particle_count += count;
+
+        for i in 0..count {
+            *seed = seed.wrapping_add(1);
+            // spread the particles out evenly, with a bit of jitter so it doesn't look too regular
+            let offset = (i as f32 + noise(*seed)) / count as f32 - 0.5;
+            let angle = emitter.direction + offset * emitter.spread;
+            let (min_speed, max_speed) = emitter.speed;
+            let speed = min_speed + (max_speed - min_speed) * noise(seed.wrapping_add(1000));
+
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: emitter.start_color,
+                        custom_size: Some(emitter.size),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(1.)),
+                    ..default()
+                },
+                Particle {
+                    velocity: Vec2::from_angle(angle) * speed,
+                    gravity: emitter.gravity,
+                    lifetime: Timer::new(emitter.lifetime, TimerMode::Once),
+                    start_color: emitter.start_color,
+                    end_color: emitter.end_color,
+                },
+            ));
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        if particle.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y -= particle.gravity * dt;
+        transform.translation += (particle.velocity * dt).extend(0.);
+        sprite.color = particle
+            .start_color
+            .mix(&particle.end_color, particle.lifetime.fraction());
+    }
+}
+
+fn cleanup_particles(mut commands: Commands, particles: Query<Entity, With<Particle>>) {
+    for entity in &particles {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// A deterministic value from 0 to 1 that looks random enough for effects.
+fn noise(seed: u32) -> f32 {
+    ((seed as f32 * 12.9898).sin() * 43758.547).fract().abs()
+}
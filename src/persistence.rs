@@ -0,0 +1,140 @@
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+/// Name of the directory the game keeps its files in, inside the user's data or config directory
+pub const APP_DIRECTORY: &str = "rage_platformer";
+
+#[derive(Debug, Error)]
+pub enum PersistenceError {
+    #[error("could not access file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse file: {0}")]
+    Parse(#[from] ron::error::SpannedError),
+    #[error("could not serialize: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+/// Reads a RON file. Returns `Ok(None)` if the file doesn't exist.
+pub fn read_ron_file<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, PersistenceError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(ron::from_str(&contents)?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Writes `value` to a RON file, creating its directory if needed. `pretty` files are meant to be
+/// readable, the others are kept compact.
+pub fn write_ron_file<T: Serialize>(
+    path: &Path,
+    value: &T,
+    pretty: bool,
+) -> Result<(), PersistenceError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = if pretty {
+        ron::ser::to_string_pretty(value, PrettyConfig::default())?
+    } else {
+        ron::to_string(value)?
+    };
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Reads a RON file, returning `None` if it doesn't exist or can't be read. A broken file is
+/// moved to [`backup_path`], so it isn't overwritten on the next save. `name` describes the file
+/// in log messages.
+pub fn load_or_backup<T: DeserializeOwned>(path: &Path, name: &str) -> Option<T> {
+    let err = match read_ron_file(path) {
+        Ok(Some(value)) => return Some(value),
+        Ok(None) => {
+            info!("No {name} found at {}, using the defaults", path.display());
+            return None;
+        }
+        Err(err) => err,
+    };
+
+    let backup = backup_path(path);
+    warn!(
+        "Failed to load {name} from {} ({err}), moving it to {} and using the defaults",
+        path.display(),
+        backup.display()
+    );
+    if let Err(err) = fs::rename(path, &backup) {
+        error!("Failed to back up {name}: {err}");
+    }
+    None
+}
+
+/// Where a broken file is moved to, e.g. `profile.ron.corrupt` for `profile.ron`.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = OsString::from(path.as_os_str());
+    backup.push(".corrupt");
+    backup.into()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// A path in the temp directory that nothing else uses, with no file or backup there yet.
+    pub fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("rage_platformer-test-{}", std::process::id()))
+            .join(name);
+        remove(&path);
+        path
+    }
+
+    pub fn remove(path: &Path) {
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(backup_path(path));
+    }
+
+    #[test]
+    fn missing_file() {
+        let path = temp_path("missing.ron");
+        assert!(read_ron_file::<Vec<u32>>(&path).unwrap().is_none());
+        assert!(load_or_backup::<Vec<u32>>(&path, "test").is_none());
+        assert!(!backup_path(&path).exists());
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("round_trip.ron");
+        write_ron_file(&path, &vec![1, 2, 3], true).unwrap();
+        let read = read_ron_file::<Vec<u32>>(&path).unwrap();
+        remove(&path);
+
+        assert_eq!(read, Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn corrupt_file_is_backed_up() {
+        let path = temp_path("corrupt.ron");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not ron").unwrap();
+
+        let loaded = load_or_backup::<Vec<u32>>(&path, "test");
+        let backed_up = backup_path(&path).exists();
+        let original_exists = path.exists();
+        remove(&path);
+
+        assert!(loaded.is_none());
+        assert!(backed_up);
+        assert!(!original_exists);
+        assert_eq!(
+            backup_path(&path).file_name().unwrap(),
+            "corrupt.ron.corrupt"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::PlaytestLevel,
+    on_level_completed,
+    persistence::{load_or_backup, write_ron_file, APP_DIRECTORY},
+    replay::ReplayPlayback,
+    DeathCounter, DeathEvent, GameState, InLevel, IsPaused, LevelCompleteEvent, LevelStopwatch,
+};
+
+const PROFILE_FILE_NAME: &str = "profile.ron";
+/// Overrides the location of the profile file, e.g. for tests or to keep several profiles.
+const PROFILE_PATH_ENV: &str = "RAGE_PLATFORMER_PROFILE";
+/// Developer setting to make every level playable regardless of progress.
+const UNLOCK_ALL_LEVELS_ENV: &str = "RAGE_PLATFORMER_UNLOCK_ALL";
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProfilePath>()
+            .init_resource::<Profile>()
+            .init_resource::<UnlockAllLevels>()
+            .add_systems(Startup, load_profile)
+            // play tests and replays aren't the player's own progress
+            .add_systems(
+                FixedUpdate,
+                // on_level_completed needs the progress from before this completion
+                record_level_completion
+                    .after(on_level_completed)
+                    .run_if(in_state(IsPaused::Unpaused))
+                    .run_if(not(resource_exists::<PlaytestLevel>))
+                    .run_if(not(resource_exists::<ReplayPlayback>)),
+            )
+            .add_systems(
+                PostUpdate,
+                record_deaths
+                    .run_if(in_state(IsPaused::Unpaused))
+                    .run_if(not(resource_exists::<PlaytestLevel>))
+                    .run_if(not(resource_exists::<ReplayPlayback>)),
+            )
+            .add_systems(OnExit(InLevel), save_profile);
+    }
+}
+
+/// Where the profile is stored. `None` disables persisting the profile.
+#[derive(Resource)]
+pub struct ProfilePath(pub Option<PathBuf>);
+
+impl Default for ProfilePath {
+    fn default() -> Self {
+        if let Some(path) = std::env::var_os(PROFILE_PATH_ENV) {
+            return Self(Some(path.into()));
+        }
+
+        Self(dirs::data_dir().map(|dir| dir.join(APP_DIRECTORY).join(PROFILE_FILE_NAME)))
+    }
+}
+
+#[derive(Resource)]
+pub struct UnlockAllLevels(pub bool);
+
+impl Default for UnlockAllLevels {
+    fn default() -> Self {
+        Self(std::env::var_os(UNLOCK_ALL_LEVELS_ENV).is_some_and(|value| value != "0"))
+    }
+}
+
+/// Progress for a single level
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelProgress {
+    pub completed: bool,
+    /// in seconds
+    pub best_time: Option<f32>,
+    pub fewest_deaths: Option<usize>,
+    pub total_deaths: usize,
+}
+
+#[derive(Debug, Default, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    levels: BTreeMap<u16, LevelProgress>,
+}
+
+impl Profile {
+    pub fn level(&self, index: u16) -> Option<&LevelProgress> {
+        self.levels.get(&index)
+    }
+
+    pub fn is_completed(&self, index: u16) -> bool {
+        self.levels.get(&index).is_some_and(|level| level.completed)
+    }
+
+    pub fn completed_count(&self) -> usize {
+        self.levels.values().filter(|level| level.completed).count()
+    }
+
+    /// Sum of the best times of all completed levels, in seconds
+    pub fn total_best_time(&self) -> f32 {
+        self.levels
+            .values()
+            .filter_map(|level| level.best_time)
+            .sum()
+    }
+
+    pub fn total_deaths(&self) -> usize {
+        self.levels.values().map(|level| level.total_deaths).sum()
+    }
+
+    pub fn record_death(&mut self, index: u16) {
+        self.levels.entry(index).or_default().total_deaths += 1;
+    }
+
+    pub fn record_completion(&mut self, index: u16, time: f32, deaths: usize) {
+        let level = self.levels.entry(index).or_default();
+        level.completed = true;
+        level.best_time = Some(level.best_time.map_or(time, |best| best.min(time)));
+        level.fewest_deaths = Some(level.fewest_deaths.map_or(deaths, |best| best.min(deaths)));
+    }
+}
+
+/// Decides which levels the player is allowed to start.
+#[derive(SystemParam)]
+pub struct LevelLocks<'w> {
+    profile: Res<'w, Profile>,
+    unlock_all: Res<'w, UnlockAllLevels>,
+}
+
+impl<'w> LevelLocks<'w> {
+    /// A level is unlocked once the level before it has been completed.
+    pub fn is_unlocked(&self, index: u16) -> bool {
+        self.unlock_all.0 || index == 0 || self.profile.is_completed(index - 1)
+    }
+}
+
+fn load_profile(mut profile: ResMut<Profile>, profile_path: Res<ProfilePath>) {
+    let Some(path) = &profile_path.0 else {
+        return;
+    };
+
+    if let Some(loaded) = load_or_backup(path, "profile") {
+        *profile = loaded;
+    }
+}
+
+fn save_profile(profile: Res<Profile>, profile_path: Res<ProfilePath>) {
+    let Some(path) = &profile_path.0 else {
+        return;
+    };
+
+    if let Err(err) = write_ron_file(path, &*profile, true) {
+        error!("Failed to save profile to {}: {err}", path.display());
+    }
+}
+
+fn record_deaths(
+    mut profile: ResMut<Profile>,
+    mut death_event_reader: EventReader<DeathEvent>,
+    game_state: Res<State<GameState>>,
+) {
+    let GameState::Level { index, .. } = **game_state else {
+        return;
+    };
+
+    for _ in death_event_reader.read() {
+        profile.record_death(index);
+    }
+}
+
+fn record_level_completion(
+    mut profile: ResMut<Profile>,
+    profile_path: Res<ProfilePath>,
+    mut level_complete_reader: EventReader<LevelCompleteEvent>,
+    game_state: Res<State<GameState>>,
+    level_stopwatch: Res<LevelStopwatch>,
+    death_counter: Res<DeathCounter>,
+) {
+    if level_complete_reader.read().count() == 0 {
+        return;
+    }
+
+    let GameState::Level { index, .. } = **game_state else {
+        return;
+    };
+    profile.record_completion(index, level_stopwatch.0.elapsed_secs(), death_counter.0);
+    save_profile(profile.into(), profile_path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{
+        read_ron_file,
+        tests::{remove, temp_path},
+    };
+
+    #[test]
+    fn completion_keeps_best_time_and_fewest_deaths() {
+        let mut profile = Profile::default();
+        profile.record_completion(0, 20., 3);
+        profile.record_completion(0, 25., 1);
+        profile.record_completion(0, 15., 5);
+
+        let level = &profile.levels[&0];
+        assert!(level.completed);
+        assert_eq!(level.best_time, Some(15.));
+        assert_eq!(level.fewest_deaths, Some(1));
+        assert!(!profile.levels.contains_key(&1));
+    }
+
+    #[test]
+    fn deaths_accumulate() {
+        let mut profile = Profile::default();
+        profile.record_death(0);
+        profile.record_death(0);
+        profile.record_death(1);
+
+        assert_eq!(profile.levels[&0].total_deaths, 2);
+        assert_eq!(profile.levels[&1].total_deaths, 1);
+        assert!(!profile.levels[&0].completed);
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("profile_round_trip.ron");
+        let mut profile = Profile::default();
+        profile.record_death(2);
+        profile.record_completion(2, 12.5, 1);
+
+        write_ron_file(&path, &profile, true).unwrap();
+        let read = read_ron_file::<Profile>(&path).unwrap().unwrap();
+        remove(&path);
+
+        let level = &read.levels[&2];
+        assert!(level.completed);
+        assert_eq!(level.best_time, Some(12.5));
+        assert_eq!(level.fewest_deaths, Some(1));
+        assert_eq!(level.total_deaths, 1);
+    }
+}
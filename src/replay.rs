@@ -0,0 +1,573 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    character_controller::{DuckInput, MovementAction, PlayerInputSet},
+    editor::PlaytestSpawn,
+    levels::{LevelData, Levels},
+    on_level_completed,
+    persistence::{read_ron_file, write_ron_file, APP_DIRECTORY},
+    settings::Settings,
+    GameState, InLevel, IsPaused, LevelCompleteEvent, LevelStopwatch, RestartRequest,
+};
+
+const REPLAY_DIRECTORY_NAME: &str = "replays";
+/// Overrides where the replays of level attempts are saved.
+const REPLAY_DIRECTORY_ENV: &str = "RAGE_PLATFORMER_REPLAY_DIR";
+/// Plays back the replay file at this path instead of taking input.
+const PLAY_REPLAY_ENV: &str = "RAGE_PLATFORMER_PLAY_REPLAY";
+/// Bump this when a change makes old replays play back differently, e.g. to the input or the
+/// player's movement.
+const REPLAY_FORMAT_VERSION: u32 = 1;
+
+/// Records a [`Replay`] of every level attempt and plays back the one given in
+/// `RAGE_PLATFORMER_PLAY_REPLAY`.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayDirectory>()
+            .configure_sets(
+                Update,
+                PlayerInputSet.run_if(not(resource_exists::<ReplayPlayback>)),
+            )
+            .add_systems(Startup, load_replay_to_play)
+            .add_systems(OnEnter(GameState::MainMenu), start_playback)
+            .add_systems(OnEnter(InLevel), start_recording)
+            // runs before the fixed timestep's gameplay, so played back input is used right away
+            .add_systems(
+                FixedPreUpdate,
+                (
+                    record_tick.run_if(resource_exists::<ReplayRecorder>),
+                    play_tick.run_if(resource_exists::<ReplayPlayback>),
+                )
+                    .run_if(in_state(IsPaused::Unpaused)),
+            )
+            .add_systems(
+                FixedUpdate,
+                record_completion_time
+                    .after(on_level_completed)
+                    .run_if(in_state(IsPaused::Unpaused)),
+            )
+            .add_systems(OnExit(InLevel), (save_recording, stop_playback));
+    }
+}
+
+/// Where replays are saved. `None` disables recording them.
+#[derive(Resource)]
+pub struct ReplayDirectory(pub Option<PathBuf>);
+
+impl Default for ReplayDirectory {
+    fn default() -> Self {
+        if let Some(path) = std::env::var_os(REPLAY_DIRECTORY_ENV) {
+            return Self(Some(path.into()));
+        }
+
+        Self(dirs::data_dir().map(|dir| dir.join(APP_DIRECTORY).join(REPLAY_DIRECTORY_NAME)))
+    }
+}
+
+/// The input of a level attempt for every fixed timestep. Since the gameplay only runs in the
+/// fixed timestep, feeding the input back in results in the same attempt.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    /// The [`REPLAY_FORMAT_VERSION`] the replay was recorded with. Replays from before it was
+    /// stored are version 0.
+    #[serde(default)]
+    pub version: u32,
+    pub level: u16,
+    /// [`level_hash`] of the level when the replay was recorded
+    #[serde(default)]
+    pub level_hash: u64,
+    /// Fixed timesteps per second
+    pub tick_rate: f64,
+    pub instant_respawn: bool,
+    /// How long it took to complete the level, in seconds. `None` if it wasn't completed.
+    pub time: Option<f32>,
+    /// Runs of identical ticks, each stored once with how many times it repeats
+    ticks: Vec<(u32, ReplayTick)>,
+}
+
+/// The input of a single fixed timestep.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct ReplayTick {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    actions: Vec<MovementAction>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    ducking: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    restarts: Vec<RestartRequest>,
+}
+
+impl Replay {
+    pub fn new(level: u16, level_hash: u64, tick_rate: f64, instant_respawn: bool) -> Self {
+        Self {
+            version: REPLAY_FORMAT_VERSION,
+            level,
+            level_hash,
+            tick_rate,
+            instant_respawn,
+            time: None,
+            ticks: Vec::new(),
+        }
+    }
+
+    pub fn tick_count(&self) -> u32 {
+        self.ticks.iter().map(|(count, _)| count).sum()
+    }
+
+    fn push(&mut self, tick: ReplayTick) {
+        match self.ticks.last_mut() {
+            Some((count, last)) if *last == tick => *count += 1,
+            _ => self.ticks.push((1, tick)),
+        }
+    }
+}
+
+/// Identifies the contents of a level, so a replay isn't played back on a level that changed
+/// since it was recorded. Uses FNV-1a, since the std hashers aren't guaranteed to stay the same
+/// between Rust versions.
+fn level_hash(level: &LevelData) -> u64 {
+    let serialized = ron::to_string(level).expect("level data can always be serialized");
+    serialized
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+/// The replay of the current level attempt.
+#[derive(Resource)]
+struct ReplayRecorder(Replay);
+
+/// A replay that is being played back. Player input is ignored while it exists.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    /// The run of ticks that is being played
+    run: usize,
+    /// How many ticks of the current run were played
+    ticks_played: u32,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            run: 0,
+            ticks_played: 0,
+        }
+    }
+
+    pub fn instant_respawn(&self) -> bool {
+        self.replay.instant_respawn
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.run >= self.replay.ticks.len()
+    }
+
+    fn next_tick(&mut self) -> Option<&ReplayTick> {
+        let (count, tick) = self.replay.ticks.get(self.run)?;
+        self.ticks_played += 1;
+        if self.ticks_played >= *count {
+            self.run += 1;
+            self.ticks_played = 0;
+        }
+        Some(tick)
+    }
+}
+
+fn load_replay_to_play(mut commands: Commands) {
+    let Some(path) = std::env::var_os(PLAY_REPLAY_ENV) else {
+        return;
+    };
+    let path = PathBuf::from(path);
+
+    match read_ron_file::<Replay>(&path) {
+        Ok(Some(replay)) => commands.insert_resource(ReplayPlayback::new(replay)),
+        Ok(None) => error!("No replay found at {}", path.display()),
+        Err(err) => error!("Failed to load replay from {}: {err}", path.display()),
+    }
+}
+
+/// Goes to the replay's level once the levels are loaded.
+fn start_playback(
+    mut commands: Commands,
+    playback: Option<Res<ReplayPlayback>>,
+    levels: Levels,
+    fixed_time: Res<Time<Fixed>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(playback) = playback else {
+        return;
+    };
+    let replay = &playback.replay;
+    if replay.version != REPLAY_FORMAT_VERSION {
+        error!(
+            "Can't play back replay, it was recorded with format version {} but the game uses \
+             {REPLAY_FORMAT_VERSION}",
+            replay.version
+        );
+        commands.remove_resource::<ReplayPlayback>();
+        return;
+    }
+    let Some(level) = levels.get(replay.level) else {
+        error!(
+            "Can't play back replay of level {}, it doesn't exist",
+            replay.level + 1
+        );
+        commands.remove_resource::<ReplayPlayback>();
+        return;
+    };
+    if level_hash(level) != replay.level_hash {
+        error!(
+            "Can't play back replay of level {}, the level changed since it was recorded",
+            replay.level + 1
+        );
+        commands.remove_resource::<ReplayPlayback>();
+        return;
+    }
+
+    let tick_rate = 1. / fixed_time.timestep().as_secs_f64();
+    if (tick_rate - replay.tick_rate).abs() > 0.01 {
+        warn!(
+            "The replay was recorded at {} ticks per second, but the game runs at {tick_rate}. \
+             It will probably not play back correctly.",
+            replay.tick_rate
+        );
+    }
+
+    info!(
+        "Playing back replay of level {} ({} ticks)",
+        replay.level + 1,
+        replay.tick_count()
+    );
+    next_state.set(GameState::level(replay.level));
+}
+
+fn stop_playback(mut commands: Commands) {
+    commands.remove_resource::<ReplayPlayback>();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_recording(
+    mut commands: Commands,
+    game_state: Res<State<GameState>>,
+    replay_directory: Res<ReplayDirectory>,
+    settings: Res<Settings>,
+    fixed_time: Res<Time<Fixed>>,
+    levels: Levels,
+    playback: Option<Res<ReplayPlayback>>,
+    // playtests start at the editor's camera, which the replay doesn't know about
+    playtest_spawn: Option<Res<PlaytestSpawn>>,
+) {
+    if replay_directory.0.is_none() || playback.is_some() || playtest_spawn.is_some() {
+        return;
+    }
+    let GameState::Level { index, .. } = **game_state else {
+        return;
+    };
+    let Some(level) = levels.get(index) else {
+        return;
+    };
+
+    let tick_rate = 1. / fixed_time.timestep().as_secs_f64();
+    commands.insert_resource(ReplayRecorder(Replay::new(
+        index,
+        level_hash(level),
+        tick_rate,
+        settings.instant_respawn,
+    )));
+}
+
+fn record_tick(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut movement_event_reader: EventReader<MovementAction>,
+    mut restart_request_reader: EventReader<RestartRequest>,
+    duck_input: Res<DuckInput>,
+) {
+    let mut actions: Vec<MovementAction> = Vec::new();
+    for action in movement_event_reader.read() {
+        // repeating an action in the same tick doesn't change anything
+        if actions.last() != Some(action) {
+            actions.push(*action);
+        }
+    }
+
+    recorder.0.push(ReplayTick {
+        actions,
+        ducking: duck_input.0,
+        restarts: restart_request_reader.read().copied().collect(),
+    });
+}
+
+fn play_tick(
+    mut playback: ResMut<ReplayPlayback>,
+    mut movement_event_writer: EventWriter<MovementAction>,
+    mut restart_request_writer: EventWriter<RestartRequest>,
+    mut duck_input: ResMut<DuckInput>,
+) {
+    let Some(tick) = playback.next_tick().cloned() else {
+        return;
+    };
+
+    movement_event_writer.send_batch(tick.actions);
+    restart_request_writer.send_batch(tick.restarts);
+    duck_input.0 = tick.ducking;
+
+    if playback.is_finished() {
+        info!("Replay finished");
+    }
+}
+
+/// Stores the time of the attempt in the replay, or checks it against the replay being played.
+fn record_completion_time(
+    mut level_complete_reader: EventReader<LevelCompleteEvent>,
+    level_stopwatch: Res<LevelStopwatch>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    playback: Option<Res<ReplayPlayback>>,
+) {
+    if level_complete_reader.read().count() == 0 {
+        return;
+    }
+    let time = level_stopwatch.0.elapsed_secs();
+
+    if let Some(mut recorder) = recorder {
+        recorder.0.time = Some(time);
+    }
+
+    let Some(playback) = playback else {
+        return;
+    };
+    // the stopwatch may or may not have been ticked in the completing timestep
+    let tolerance = 1. / playback.replay.tick_rate as f32 + 0.001;
+    match playback.replay.time {
+        Some(recorded) if (recorded - time).abs() <= tolerance => {
+            info!("Replay completed the level in {time:.3}s, like the recorded attempt")
+        }
+        Some(recorded) => warn!(
+            "Replay completed the level in {time:.3}s, but the recorded attempt took {recorded:.3}s"
+        ),
+        None => warn!(
+            "Replay completed the level in {time:.3}s, but the recorded attempt didn't complete it"
+        ),
+    }
+}
+
+fn save_recording(
+    mut commands: Commands,
+    recorder: Option<Res<ReplayRecorder>>,
+    replay_directory: Res<ReplayDirectory>,
+) {
+    let Some(recorder) = recorder else {
+        return;
+    };
+    commands.remove_resource::<ReplayRecorder>();
+
+    let replay = &recorder.0;
+    let Some(directory) = &replay_directory.0 else {
+        return;
+    };
+    if replay.tick_count() == 0 {
+        return;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let path = directory.join(format!("level-{}-{timestamp}.ron", replay.level + 1));
+    // replays are long and not meant to be edited by hand, so keep them compact
+    match write_ron_file(&path, replay, false) {
+        Ok(()) => info!("Saved replay to {}", path.display()),
+        Err(err) => error!("Failed to save replay to {}: {err}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(ducking: bool) -> ReplayTick {
+        ReplayTick {
+            ducking,
+            ..default()
+        }
+    }
+
+    fn replay(ticks: impl IntoIterator<Item = ReplayTick>) -> Replay {
+        let mut replay = Replay::new(0, 0, 60., false);
+        for tick in ticks {
+            replay.push(tick);
+        }
+        replay
+    }
+
+    /// Sends the actions of one frame per update, like the player's input.
+    #[derive(Resource)]
+    struct ScriptedInput(std::vec::IntoIter<Vec<MovementAction>>);
+
+    fn scripted_input(
+        mut input: ResMut<ScriptedInput>,
+        mut movement_event_writer: EventWriter<MovementAction>,
+    ) {
+        if let Some(actions) = input.0.next() {
+            movement_event_writer.send_batch(actions);
+        }
+    }
+
+    /// The actions the gameplay saw in each fixed timestep
+    #[derive(Resource, Default)]
+    struct ReceivedActions(Vec<Vec<MovementAction>>);
+
+    fn receive_actions(
+        mut received: ResMut<ReceivedActions>,
+        mut movement_event_reader: EventReader<MovementAction>,
+    ) {
+        received
+            .0
+            .push(movement_event_reader.read().copied().collect());
+    }
+
+    /// Wires up the input, recording and playback like [`ReplayPlugin`], without the rest of the
+    /// game.
+    fn input_app() -> App {
+        let mut app = App::new();
+        app.add_event::<MovementAction>()
+            .add_event::<RestartRequest>()
+            .init_resource::<DuckInput>()
+            .init_resource::<ReceivedActions>()
+            .configure_sets(
+                Update,
+                PlayerInputSet.run_if(not(resource_exists::<ReplayPlayback>)),
+            )
+            .add_systems(Update, scripted_input.in_set(PlayerInputSet))
+            .add_systems(
+                FixedPreUpdate,
+                (
+                    record_tick.run_if(resource_exists::<ReplayRecorder>),
+                    play_tick.run_if(resource_exists::<ReplayPlayback>),
+                ),
+            )
+            .add_systems(FixedUpdate, receive_actions);
+        app
+    }
+
+    /// A frame with exactly one fixed timestep
+    fn run_frame(app: &mut App) {
+        let world = app.world_mut();
+        world.run_schedule(Update);
+        world.run_schedule(FixedPreUpdate);
+        world.run_schedule(FixedUpdate);
+    }
+
+    #[test]
+    fn identical_ticks_are_merged() {
+        let replay = replay([tick(false), tick(false), tick(true), tick(false)]);
+
+        assert_eq!(
+            replay.ticks,
+            vec![(2, tick(false)), (1, tick(true)), (1, tick(false))]
+        );
+        assert_eq!(replay.tick_count(), 4);
+    }
+
+    #[test]
+    fn playback_follows_runs() {
+        let mut playback =
+            ReplayPlayback::new(replay([tick(false), tick(false), tick(true), tick(false)]));
+
+        let mut played = Vec::new();
+        while let Some(tick) = playback.next_tick() {
+            played.push(tick.ducking);
+        }
+
+        assert_eq!(played, [false, false, true, false]);
+        assert!(playback.is_finished());
+    }
+
+    #[test]
+    fn playback_finishes_after_last_tick() {
+        let mut playback = ReplayPlayback::new(replay([tick(false), tick(false)]));
+
+        assert!(playback.next_tick().is_some());
+        assert!(!playback.is_finished());
+        assert!(playback.next_tick().is_some());
+        assert!(playback.is_finished());
+        assert!(playback.next_tick().is_none());
+    }
+
+    #[test]
+    fn empty_replay() {
+        let replay = replay([]);
+        assert_eq!(replay.tick_count(), 0);
+
+        let mut playback = ReplayPlayback::new(replay);
+        assert!(playback.is_finished());
+        assert!(playback.next_tick().is_none());
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let mut replay = replay([
+            tick(false),
+            ReplayTick {
+                actions: vec![MovementAction::Jump],
+                ducking: false,
+                restarts: vec![RestartRequest::LastSave],
+            },
+            tick(true),
+            tick(true),
+        ]);
+        replay.time = Some(12.5);
+
+        let serialized = ron::to_string(&replay).unwrap();
+        let deserialized: Replay = ron::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, replay);
+    }
+
+    #[test]
+    fn playback_reproduces_recorded_actions() {
+        let frames = vec![
+            vec![MovementAction::Move(1.)],
+            vec![],
+            vec![MovementAction::Move(1.), MovementAction::Jump],
+            vec![MovementAction::Move(-0.5)],
+            vec![MovementAction::Move(-0.5)],
+            vec![],
+        ];
+
+        let mut recording = input_app();
+        recording
+            .insert_resource(ScriptedInput(frames.clone().into_iter()))
+            .insert_resource(ReplayRecorder(Replay::new(0, 0, 60., false)));
+        for _ in &frames {
+            run_frame(&mut recording);
+        }
+        let world = recording.world_mut();
+        let recorded_actions = world.remove_resource::<ReceivedActions>().unwrap().0;
+        let replay = world.remove_resource::<ReplayRecorder>().unwrap().0;
+        assert_eq!(recorded_actions, frames);
+
+        let mut playback = input_app();
+        playback
+            // the player's input is ignored while the replay plays
+            .insert_resource(ScriptedInput(
+                vec![vec![MovementAction::Jump]; frames.len()].into_iter(),
+            ))
+            .insert_resource(ReplayPlayback::new(replay));
+        for _ in &frames {
+            run_frame(&mut playback);
+        }
+        let world = playback.world_mut();
+        assert!(world.resource::<ReplayPlayback>().is_finished());
+        let played_actions = world.remove_resource::<ReceivedActions>().unwrap().0;
+        assert_eq!(played_actions, recorded_actions);
+    }
+}
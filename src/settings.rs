@@ -0,0 +1,114 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{update_action_state, Action, InputMap},
+    persistence::{load_or_backup, write_ron_file, APP_DIRECTORY},
+};
+
+const SETTINGS_FILE_NAME: &str = "settings.ron";
+/// Overrides the location of the settings file.
+const SETTINGS_PATH_ENV: &str = "RAGE_PLATFORMER_SETTINGS";
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsPath>()
+            .init_resource::<Settings>()
+            .add_systems(Startup, load_settings)
+            .add_systems(
+                PreUpdate,
+                // rebound keys have to be in place before they're read this frame
+                apply_key_bindings
+                    .before(update_action_state)
+                    .run_if(resource_changed::<Settings>),
+            );
+    }
+}
+
+/// Where the settings are stored. `None` disables persisting them.
+#[derive(Resource)]
+pub struct SettingsPath(pub Option<PathBuf>);
+
+impl Default for SettingsPath {
+    fn default() -> Self {
+        if let Some(path) = std::env::var_os(SETTINGS_PATH_ENV) {
+            return Self(Some(path.into()));
+        }
+
+        Self(dirs::config_dir().map(|dir| dir.join(APP_DIRECTORY).join(SETTINGS_FILE_NAME)))
+    }
+}
+
+#[derive(Debug, Resource, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Keys for the actions that were rebound. Other actions use the default keys.
+    pub key_bindings: BTreeMap<Action, Vec<KeyCode>>,
+    pub volume: VolumeSettings,
+    /// Shake the camera when the player dies
+    pub screen_shake: bool,
+    /// Respawn right away instead of after the death animation
+    pub instant_respawn: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            key_bindings: BTreeMap::new(),
+            volume: VolumeSettings::default(),
+            screen_shake: true,
+            instant_respawn: false,
+        }
+    }
+}
+
+/// Volume multipliers from 0 to 1.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumeSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.,
+            music: 1.,
+            sfx: 1.,
+        }
+    }
+}
+
+fn load_settings(mut settings: ResMut<Settings>, settings_path: Res<SettingsPath>) {
+    let Some(path) = &settings_path.0 else {
+        return;
+    };
+
+    if let Some(loaded) = load_or_backup(path, "settings") {
+        *settings = loaded;
+    }
+}
+
+/// Writes the settings to disk. Call this after changing them.
+pub fn save_settings(settings: &Settings, settings_path: &SettingsPath) {
+    let Some(path) = &settings_path.0 else {
+        return;
+    };
+
+    if let Err(err) = write_ron_file(path, settings, true) {
+        error!("Failed to save settings to {}: {err}", path.display());
+    }
+}
+
+fn apply_key_bindings(settings: Res<Settings>, mut input_map: ResMut<InputMap>) {
+    input_map.keys = InputMap::default().keys;
+    for (action, keys) in &settings.key_bindings {
+        input_map.keys.insert(*action, keys.clone());
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::{
+    audio::{PlaybackMode, Volume},
+    prelude::*,
+    utils::HashMap,
+};
+
+use crate::settings::{Settings, VolumeSettings};
+
+/// How long it takes to fade from one music track to the next, in seconds
+const MUSIC_FADE_TIME: f32 = 1.;
+const MUSIC_VOLUME: f32 = 0.2;
+
+/// Plays sound effects sent with [`PlaySfx`] and crossfades to the [`CurrentMusic`].
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlaySfx>()
+            .init_resource::<CurrentMusic>()
+            .add_systems(Startup, load_sound_effects)
+            .add_systems(Last, (play_sound_effects, update_music));
+    }
+}
+
+/// Which volume setting applies to a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundCategory {
+    Music,
+    Sfx,
+}
+
+impl SoundCategory {
+    /// The volume to play a sound at that is played at `volume` with the volume all the way up.
+    pub fn volume(self, settings: &VolumeSettings, volume: f32) -> Volume {
+        let category = match self {
+            SoundCategory::Music => settings.music,
+            SoundCategory::Sfx => settings.sfx,
+        };
+        Volume::new(volume * settings.master * category)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sfx {
+    Death,
+    CheckpointActivate,
+    LevelComplete,
+    UiClick,
+}
+
+impl Sfx {
+    const ALL: [Sfx; 4] = [
+        Sfx::Death,
+        Sfx::CheckpointActivate,
+        Sfx::LevelComplete,
+        Sfx::UiClick,
+    ];
+
+    fn path(self) -> &'static str {
+        match self {
+            Sfx::Death => "player_death.ogg",
+            Sfx::CheckpointActivate => "checkpoint_activate.ogg",
+            Sfx::LevelComplete => "level_complete.ogg",
+            Sfx::UiClick => "ui_click.ogg",
+        }
+    }
+
+    fn volume(self) -> f32 {
+        match self {
+            Sfx::Death | Sfx::CheckpointActivate => 0.3,
+            Sfx::LevelComplete => 0.5,
+            Sfx::UiClick => 0.2,
+        }
+    }
+
+    /// How many instances of this sound can play at the same time. Playing another one stops
+    /// the oldest.
+    fn max_instances(self) -> usize {
+        match self {
+            Sfx::Death | Sfx::CheckpointActivate => 2,
+            Sfx::LevelComplete => 1,
+            Sfx::UiClick => 3,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct PlaySfx(pub Sfx);
+
+/// The path of the music track that should be playing, if any.
+#[derive(Resource, Default, PartialEq)]
+pub struct CurrentMusic(pub Option<String>);
+
+/// A looping music track. It's faded out and despawned once it isn't the [`CurrentMusic`]
+/// anymore.
+#[derive(Component)]
+pub struct Music {
+    track: String,
+    /// from 0 (silent) to 1
+    fade: f32,
+}
+
+#[derive(Resource)]
+struct SfxHandles(HashMap<Sfx, Handle<AudioSource>>);
+
+/// A sound effect that is playing, it despawns when it's done.
+#[derive(Component)]
+struct PlayingSfx {
+    sfx: Sfx,
+    /// in seconds since startup
+    started: f64,
+}
+
+fn load_sound_effects(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = Sfx::ALL
+        .into_iter()
+        .map(|sfx| (sfx, asset_server.load(sfx.path())))
+        .collect();
+    commands.insert_resource(SfxHandles(handles));
+}
+
+fn play_sound_effects(
+    mut commands: Commands,
+    mut play_sfx_reader: EventReader<PlaySfx>,
+    handles: Res<SfxHandles>,
+    settings: Res<Settings>,
+    time: Res<Time<Real>>,
+    playing: Query<(Entity, &PlayingSfx)>,
+) {
+    let mut requested: Vec<Sfx> = play_sfx_reader.read().map(|event| event.0).collect();
+    // the same sound played several times in one frame would only be louder
+    requested.sort_by_key(|sfx| *sfx as u8);
+    requested.dedup();
+
+    let now = time.elapsed_seconds_f64();
+    for sfx in requested {
+        let mut instances: Vec<_> = playing
+            .iter()
+            .filter(|(_, playing)| playing.sfx == sfx)
+            .map(|(entity, playing)| (entity, playing.started))
+            .collect();
+        instances.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        let excess = (instances.len() + 1).saturating_sub(sfx.max_instances());
+        for (entity, _) in instances.into_iter().take(excess) {
+            commands.entity(entity).despawn();
+        }
+
+        commands.spawn((
+            AudioBundle {
+                source: handles.0[&sfx].clone(),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Despawn,
+                    volume: SoundCategory::Sfx.volume(&settings.volume, sfx.volume()),
+                    ..default()
+                },
+            },
+            PlayingSfx { sfx, started: now },
+        ));
+    }
+}
+
+fn update_music(
+    mut commands: Commands,
+    current_music: Res<CurrentMusic>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    time: Res<Time<Real>>,
+    mut players: Query<(Entity, &mut Music, Option<&AudioSink>)>,
+) {
+    let current_track = current_music.0.as_deref();
+    if let Some(track) = current_track {
+        if !players.iter().any(|(_, music, _)| music.track == track) {
+            commands.spawn((
+                AudioBundle {
+                    source: asset_server.load(track.to_string()),
+                    settings: PlaybackSettings::LOOP.with_volume(Volume::new(0.)),
+                },
+                Music {
+                    track: track.to_string(),
+                    fade: 0.,
+                },
+            ));
+        }
+    }
+
+    let fade_step = time.delta_seconds() / MUSIC_FADE_TIME;
+    for (entity, mut music, sink) in &mut players {
+        // tracks that become current again while fading out fade back in
+        let fade = if current_track == Some(music.track.as_str()) {
+            (music.fade + fade_step).min(1.)
+        } else {
+            music.fade - fade_step
+        };
+        if fade <= 0. {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        music.fade = fade;
+        if let Some(sink) = sink {
+            sink.set_volume(*SoundCategory::Music.volume(&settings.volume, MUSIC_VOLUME * fade));
+        }
+    }
+}
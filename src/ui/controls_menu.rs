@@ -0,0 +1,309 @@
+use bevy::{color::palettes::css::*, prelude::*};
+
+use super::*;
+use crate::{
+    actions::{Action, InputMap},
+    settings::{save_settings, Settings, SettingsPath},
+};
+
+/// How many keys can be bound to each action
+const KEY_SLOTS: usize = 3;
+
+pub struct ControlsMenuPlugin;
+
+impl Plugin for ControlsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_systems(OnEnter(SubMenu::Controls), setup_controls_menu)
+            .add_systems(OnExit(SubMenu::Controls), cleanup_controls_menu)
+            .add_systems(
+                Update,
+                (
+                    button_interaction::<BindingButton>.pipe(binding_button_system),
+                    block_navigation_while_rebinding,
+                    button_interaction::<ResetButton>.pipe(reset_button_system),
+                    button_interaction::<BackButton>.pipe(back_button_system),
+                    rebind_key_system,
+                    update_binding_buttons,
+                )
+                    .chain()
+                    .run_if(in_state(SubMenu::Controls)),
+            );
+    }
+}
+
+/// The key slot that waits for a key press to be rebound.
+#[derive(Resource, Default)]
+struct Rebinding(Option<BindingButton>);
+
+#[derive(Component)]
+struct ControlsMenu;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+struct BindingButton {
+    action: Action,
+    slot: usize,
+}
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Component)]
+struct ResetButton;
+
+#[derive(Component)]
+struct BackButton;
+
+fn setup_controls_menu(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    rebinding.0 = None;
+
+    spawn_root_node(&mut commands)
+        .insert(ControlsMenu)
+        .insert(BackgroundColor(BLACK.with_alpha(0.8).into()))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Controls",
+                TextStyle {
+                    font_size: 50.,
+                    color: WHITE.into(),
+                    ..default()
+                },
+            ));
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(30.));
+
+            for action in Action::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: UiRect::vertical(Val::Px(4.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(
+                                action.name(),
+                                TextStyle {
+                                    font_size: 25.,
+                                    color: WHITE.into(),
+                                    ..default()
+                                },
+                            )
+                            .with_style(Style {
+                                width: Val::Px(220.),
+                                ..default()
+                            }),
+                        );
+                        for slot in 0..KEY_SLOTS {
+                            let button = BindingButton { action, slot };
+                            spawn_button(parent, binding_label(&input_map, None, button))
+                                .insert(button)
+                                .insert(Style {
+                                    width: Val::Px(180.),
+                                    padding: UiRect::all(Val::Px(5.)),
+                                    margin: UiRect::left(Val::Px(10.)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                });
+                        }
+                    });
+            }
+
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            parent.spawn((
+                TextBundle::from_section(
+                    "Click a key to rebind it, Backspace removes it and Escape cancels",
+                    TextStyle {
+                        font_size: 20.,
+                        color: GREY.into(),
+                        ..default()
+                    },
+                ),
+                StatusText,
+            ));
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            spawn_button(parent, "Reset to Defaults").insert(ResetButton);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            spawn_button(parent, "Back").insert(BackButton);
+        });
+}
+
+fn cleanup_controls_menu(
+    mut commands: Commands,
+    entities: Query<Entity, With<ControlsMenu>>,
+    mut focus: ResMut<MenuFocus>,
+) {
+    focus.blocked = false;
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn binding_label(
+    input_map: &InputMap,
+    rebinding: Option<BindingButton>,
+    button: BindingButton,
+) -> String {
+    if rebinding == Some(button) {
+        return "Press a key...".to_string();
+    }
+
+    input_map
+        .keys
+        .get(&button.action)
+        .and_then(|keys| keys.get(button.slot))
+        .map_or_else(|| "-".to_string(), |key| key_name(*key))
+}
+
+/// A short, readable name for `key`, e.g. "A" instead of "KeyA".
+fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+    ["Key", "Digit"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix).map(str::to_string))
+        .unwrap_or(name)
+}
+
+fn binding_button_system(
+    In(released): In<ButtonInteractionResult>,
+    buttons: Query<&BindingButton>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    let Some(entity) = released else {
+        return;
+    };
+
+    rebinding.0 = buttons.get(entity).ok().copied();
+}
+
+/// Keeps the arrow keys and Enter from moving the focus while they are being bound.
+fn block_navigation_while_rebinding(rebinding: Res<Rebinding>, mut focus: ResMut<MenuFocus>) {
+    focus.blocked = rebinding.0.is_some();
+}
+
+fn reset_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut settings: ResMut<Settings>,
+    settings_path: Res<SettingsPath>,
+    mut rebinding: ResMut<Rebinding>,
+    mut status_text: Query<&mut Text, With<StatusText>>,
+) {
+    if released.is_none() {
+        return;
+    }
+
+    rebinding.0 = None;
+    settings.key_bindings.clear();
+    save_settings(&settings, &settings_path);
+    for mut text in &mut status_text {
+        text.sections[0].value = "Restored the default controls".to_string();
+    }
+}
+
+fn back_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_sub_menu: ResMut<NextState<SubMenu>>,
+) {
+    if released.is_some() {
+        next_sub_menu.set(SubMenu::None);
+    }
+}
+
+/// Binds the next pressed key to the slot that is being rebound. Keys that are already bound to
+/// another action are rejected.
+fn rebind_key_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input_map: Res<InputMap>,
+    mut settings: ResMut<Settings>,
+    settings_path: Res<SettingsPath>,
+    mut rebinding: ResMut<Rebinding>,
+    mut next_sub_menu: ResMut<NextState<SubMenu>>,
+    mut status_text: Query<&mut Text, With<StatusText>>,
+) {
+    // the key that started rebinding the slot shouldn't be bound to it
+    if rebinding.is_changed() {
+        return;
+    }
+    let Some(key) = keyboard_input.get_just_pressed().next().copied() else {
+        return;
+    };
+    let Some(button) = rebinding.0 else {
+        if key == KeyCode::Escape {
+            next_sub_menu.set(SubMenu::None);
+        }
+        return;
+    };
+    rebinding.0 = None;
+
+    let mut keys = input_map
+        .keys
+        .get(&button.action)
+        .cloned()
+        .unwrap_or_default();
+    let status = match key {
+        KeyCode::Escape => return,
+        KeyCode::Backspace => {
+            if button.slot < keys.len() {
+                keys.remove(button.slot);
+            }
+            format!("Removed a key from {}", button.action.name())
+        }
+        _ => {
+            let conflict = input_map
+                .keys
+                .iter()
+                .find(|(action, keys)| **action != button.action && keys.contains(&key));
+            if let Some((other, _)) = conflict {
+                for mut text in &mut status_text {
+                    text.sections[0].value = format!(
+                        "{} is already bound to {}, remove it there first",
+                        key_name(key),
+                        other.name()
+                    );
+                }
+                return;
+            }
+
+            if keys.contains(&key) {
+                return;
+            }
+            if button.slot < keys.len() {
+                keys[button.slot] = key;
+            } else {
+                keys.push(key);
+            }
+            format!("Bound {} to {}", key_name(key), button.action.name())
+        }
+    };
+
+    settings.key_bindings.insert(button.action, keys);
+    save_settings(&settings, &settings_path);
+    for mut text in &mut status_text {
+        text.sections[0].value = status.clone();
+    }
+}
+
+fn update_binding_buttons(
+    input_map: Res<InputMap>,
+    rebinding: Res<Rebinding>,
+    buttons: Query<(&BindingButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !input_map.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+
+    for (button, children) in &buttons {
+        let mut iter = texts.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = binding_label(&input_map, rebinding.0, *button);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+
+use super::*;
+use crate::{levels::Levels, profile::Profile, GameState};
+
+pub struct GameCompleteMenuPlugin;
+
+impl Plugin for GameCompleteMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::GameComplete), setup_game_complete_menu)
+            .add_systems(OnExit(GameState::GameComplete), cleanup_game_complete_menu)
+            .add_systems(
+                Update,
+                button_interaction::<MainMenuButton>
+                    .pipe(main_menu_button_system)
+                    .run_if(in_state(GameState::GameComplete)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct GameCompleteMenu;
+
+#[derive(Component)]
+struct MainMenuButton;
+
+fn setup_game_complete_menu(mut commands: Commands, profile: Res<Profile>, levels: Levels) {
+    spawn_root_node(&mut commands)
+        .insert(GameCompleteMenu)
+        .with_children(|parent| {
+            spawn_text(parent, "You beat Rage Platformer!", 50.);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(30.));
+            spawn_text(
+                parent,
+                format!(
+                    "Levels completed: {}/{}",
+                    profile.completed_count(),
+                    levels.count()
+                ),
+                30.,
+            );
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(10.));
+            spawn_text(
+                parent,
+                format!("Total time (best runs): {:.1}s", profile.total_best_time()),
+                30.,
+            );
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(10.));
+            spawn_text(
+                parent,
+                format!("Total deaths: {}", profile.total_deaths()),
+                30.,
+            );
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(50.));
+            spawn_text(parent, "Thanks for playing!", 25.);
+            spawn_text(parent, "Made with Bevy and Avian", 20.);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(50.));
+            spawn_button(parent, "Main Menu").insert(MainMenuButton);
+        });
+}
+
+fn cleanup_game_complete_menu(
+    mut commands: Commands,
+    entities: Query<Entity, With<GameCompleteMenu>>,
+) {
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn main_menu_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if released.is_some() {
+        next_game_state.set(GameState::MainMenu);
+    }
+}
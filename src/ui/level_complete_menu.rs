@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+
+use super::*;
+use crate::{levels::Levels, profile::LevelLocks, GameState, LevelResults};
+
+pub struct LevelCompleteMenuPlugin;
+
+impl Plugin for LevelCompleteMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::LevelComplete), setup_level_complete_menu)
+            .add_systems(
+                OnExit(GameState::LevelComplete),
+                cleanup_level_complete_menu,
+            )
+            .add_systems(
+                Update,
+                (
+                    button_interaction::<NextLevelButton>.pipe(next_level_button_system),
+                    button_interaction::<FinishButton>.pipe(finish_button_system),
+                    button_interaction::<RetryButton>.pipe(retry_button_system),
+                    button_interaction::<LevelSelectButton>.pipe(level_select_button_system),
+                )
+                    .run_if(in_state(GameState::LevelComplete)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct LevelCompleteMenu;
+
+#[derive(Component)]
+struct NextLevelButton;
+
+#[derive(Component)]
+struct FinishButton;
+
+#[derive(Component)]
+struct RetryButton;
+
+#[derive(Component)]
+struct LevelSelectButton;
+
+fn setup_level_complete_menu(
+    mut commands: Commands,
+    results: Res<LevelResults>,
+    levels: Levels,
+    level_locks: LevelLocks,
+) {
+    let previous = results.previous_progress.as_ref();
+    let time_text = format_result(
+        format!("Time: {:.1}s", results.time),
+        results.time,
+        previous.and_then(|p| p.best_time),
+        |best| format!("{best:.1}s"),
+    );
+    let deaths_text = format_result(
+        format!("Deaths: {}", results.deaths),
+        results.deaths,
+        previous.and_then(|p| p.fewest_deaths),
+        |best| best.to_string(),
+    );
+
+    spawn_root_node(&mut commands)
+        .insert(LevelCompleteMenu)
+        .with_children(|parent| {
+            spawn_text(
+                parent,
+                format!("Level {} complete!", results.index + 1),
+                50.,
+            );
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(30.));
+            spawn_text(parent, time_text, 30.);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(10.));
+            spawn_text(parent, deaths_text, 30.);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(50.));
+
+            let next_index = results.index + 1;
+            if next_index < levels.count() {
+                // completions in replays aren't recorded in the profile, so it can still be locked
+                if level_locks.is_unlocked(next_index) {
+                    spawn_button(parent, "Next Level").insert(NextLevelButton);
+                } else {
+                    spawn_button(parent, "Next Level (locked)").insert((
+                        NextLevelButton,
+                        Disabled,
+                        BackgroundColor(DISABLED_BUTTON.into()),
+                    ));
+                }
+            } else {
+                spawn_button(parent, "Finish").insert(FinishButton);
+            }
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            spawn_button(parent, "Retry").insert(RetryButton);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            spawn_button(parent, "Select Level").insert(LevelSelectButton);
+        });
+}
+
+/// Appends how `value` compares to the previous personal best, where lower is better.
+fn format_result<T: PartialOrd + Copy>(
+    label: String,
+    value: T,
+    previous_best: Option<T>,
+    format_best: impl Fn(T) -> String,
+) -> String {
+    match previous_best {
+        Some(best) if best <= value => format!("{label} (best: {})", format_best(best)),
+        Some(best) => format!("{label} - new best! (was {})", format_best(best)),
+        None => format!("{label} - new best!"),
+    }
+}
+
+fn cleanup_level_complete_menu(
+    mut commands: Commands,
+    entities: Query<Entity, With<LevelCompleteMenu>>,
+) {
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn next_level_button_system(
+    In(released): In<ButtonInteractionResult>,
+    results: Res<LevelResults>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    level_locks: LevelLocks,
+) {
+    if released.is_some() && level_locks.is_unlocked(results.index + 1) {
+        next_game_state.set(GameState::level(results.index + 1));
+    }
+}
+
+fn finish_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if released.is_some() {
+        next_game_state.set(GameState::GameComplete);
+    }
+}
+
+fn retry_button_system(
+    In(released): In<ButtonInteractionResult>,
+    results: Res<LevelResults>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if released.is_some() {
+        next_game_state.set(GameState::level(results.index));
+    }
+}
+
+fn level_select_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if released.is_some() {
+        next_game_state.set(GameState::LevelSelect);
+    }
+}
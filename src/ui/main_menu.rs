@@ -15,6 +15,9 @@ impl Plugin for MainMenuPlugin {
                 (
                     button_interaction::<PlayButton>.pipe(play_button_system),
                     button_interaction::<LevelSelectButton>.pipe(level_select_button_system),
+                    button_interaction::<EditorButton>.pipe(editor_button_system),
+                    button_interaction::<ControlsButton>.pipe(controls_button_system),
+                    button_interaction::<SettingsButton>.pipe(settings_button_system),
                     button_interaction::<QuitButton>.pipe(quit_button_system),
                 )
                     .run_if(in_state(GameState::MainMenu)),
@@ -31,12 +34,21 @@ struct PlayButton;
 #[derive(Component)]
 struct LevelSelectButton;
 
+#[derive(Component)]
+struct EditorButton;
+
+#[derive(Component)]
+struct ControlsButton;
+
+#[derive(Component)]
+struct SettingsButton;
+
 #[derive(Component)]
 struct QuitButton;
 
 fn setup_main_menu(mut commands: Commands) {
     spawn_root_node(&mut commands)
-        .insert(MainMenu)
+        .insert((MainMenu, HiddenInSubMenu))
         .with_children(|parent| {
             parent.spawn(TextBundle::from_section(
                 "Rage Platformer",
@@ -51,6 +63,12 @@ fn setup_main_menu(mut commands: Commands) {
             spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
             spawn_button(parent, "Select Level").insert(LevelSelectButton);
             spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            spawn_button(parent, "Level Editor").insert(EditorButton);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            spawn_button(parent, "Controls").insert(ControlsButton);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            spawn_button(parent, "Settings").insert(SettingsButton);
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
             spawn_button(parent, "Quit").insert(QuitButton);
         });
 }
@@ -79,6 +97,33 @@ fn level_select_button_system(
     }
 }
 
+fn editor_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+) {
+    if released.is_some() {
+        next_game_state.set(GameState::Editor { index: 0 });
+    }
+}
+
+fn settings_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_sub_menu: ResMut<NextState<SubMenu>>,
+) {
+    if released.is_some() {
+        next_sub_menu.set(SubMenu::Settings);
+    }
+}
+
+fn controls_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_sub_menu: ResMut<NextState<SubMenu>>,
+) {
+    if released.is_some() {
+        next_sub_menu.set(SubMenu::Controls);
+    }
+}
+
 fn quit_button_system(In(released): In<ButtonInteractionResult>, mut exit: EventWriter<AppExit>) {
     if released.is_some() {
         exit.send(AppExit::Success);
@@ -87,7 +132,8 @@ fn quit_button_system(In(released): In<ButtonInteractionResult>, mut exit: Event
 
 mod level_select_menu {
     use super::super::*;
-    use crate::levels::LevelGenerator;
+    use crate::levels::Levels;
+    use crate::profile::LevelLocks;
     use crate::GameState;
 
     #[derive(Resource)]
@@ -106,6 +152,7 @@ mod level_select_menu {
                         button_interaction::<BackButton>.pipe(back_button_system),
                         button_interaction::<ArrowButton>.pipe(arrow_button_system),
                         button_interaction::<LevelButton>.pipe(level_button_system),
+                        update_level_buttons,
                     )
                         .run_if(in_state(GameState::LevelSelect)),
                 );
@@ -127,7 +174,8 @@ mod level_select_menu {
     #[derive(Component)]
     pub struct LevelButton(u16);
 
-    pub fn setup_menu(mut commands: Commands) {
+    pub fn setup_menu(mut commands: Commands, levels: Levels) {
+        let level_count = levels.count();
         spawn_root_node(&mut commands)
             .insert(LevelSelectMenu)
             .with_children(|parent| {
@@ -187,7 +235,7 @@ mod level_select_menu {
                                 .with_children(|parent| {
                                     for j in 0..3 {
                                         let level_idx = j * 3 + i;
-                                        if level_idx >= LevelGenerator::level_count() {
+                                        if level_idx >= level_count {
                                             spawn_sized_box(
                                                 parent,
                                                 Val::Percent(100.),
@@ -274,8 +322,8 @@ mod level_select_menu {
         In(released): In<ButtonInteractionResult>,
         mut page: ResMut<LevelSelectPage>,
         arrow_buttons: Query<(Entity, &ArrowButton)>,
-        mut level_buttons: Query<(&mut LevelButton, &Children)>,
-        mut texts: Query<&mut Text>,
+        mut level_buttons: Query<&mut LevelButton>,
+        levels: Levels,
     ) {
         let Some(released) = released else {
             return;
@@ -289,7 +337,7 @@ mod level_select_menu {
 
                 match button {
                     ArrowButton::Forward => {
-                        if (page.0 + 1) * 9 >= LevelGenerator::level_count() {
+                        if (page.0 + 1) * 9 >= levels.count() {
                             return;
                         }
 
@@ -309,15 +357,41 @@ mod level_select_menu {
             unreachable!();
         };
 
-        for (mut button, children) in &mut level_buttons {
-            let mut text = texts.get_mut(*children.first().unwrap()).unwrap();
+        for mut button in &mut level_buttons {
             if page_delta > 0 {
                 button.0 += 9;
             } else {
                 button.0 -= 9;
             }
+        }
+    }
 
-            text.sections[0].value = format!("Level {}", button.0 + 1);
+    /// Updates the label and lock state of level buttons when they are spawned or moved to
+    /// another page.
+    pub fn update_level_buttons(
+        mut commands: Commands,
+        mut level_buttons: Query<
+            (Entity, &LevelButton, &Children, &mut BackgroundColor),
+            Changed<LevelButton>,
+        >,
+        mut texts: Query<&mut Text>,
+        level_locks: LevelLocks,
+    ) {
+        for (entity, button, children, mut bg) in &mut level_buttons {
+            let mut text = texts.get_mut(*children.first().unwrap()).unwrap();
+            let section = &mut text.sections[0];
+
+            if level_locks.is_unlocked(button.0) {
+                commands.entity(entity).remove::<Disabled>();
+                section.value = format!("Level {}", button.0 + 1);
+                section.style.color = WHITE.into();
+                *bg = NORMAL_BUTTON.into();
+            } else {
+                commands.entity(entity).insert(Disabled);
+                section.value = format!("Level {} (locked)", button.0 + 1);
+                section.style.color = DISABLED_TEXT.into();
+                *bg = DISABLED_BUTTON.into();
+            }
         }
     }
 
@@ -325,6 +399,7 @@ mod level_select_menu {
         In(released): In<ButtonInteractionResult>,
         level_buttons: Query<(Entity, &LevelButton)>,
         mut next_game_state: ResMut<NextState<GameState>>,
+        level_locks: LevelLocks,
     ) {
         let Some(released) = released else {
             return;
@@ -335,6 +410,9 @@ mod level_select_menu {
                 continue;
             }
 
+            if !level_locks.is_unlocked(button.0) {
+                return;
+            }
             next_game_state.set(GameState::level(button.0));
             return;
         }
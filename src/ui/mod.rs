@@ -1,29 +1,71 @@
-use bevy::{
-    audio::{PlaybackMode, Volume},
-    color::palettes::css::*,
-    ecs::system::EntityCommands,
-    prelude::*,
+use bevy::{color::palettes::css::*, ecs::system::EntityCommands, input::InputSystem, prelude::*};
+
+use crate::{
+    sound::{PlaySfx, Sfx},
+    GameState, IsPaused,
 };
 
+pub mod controls_menu;
+pub mod game_complete_menu;
+pub mod level_complete_menu;
 pub mod main_menu;
 pub mod pause_menu;
+pub mod settings_menu;
 
 const BUTTON_WIDTH: Val = Val::Percent(20.);
 const BUTTON_PADDING: UiRect = UiRect::all(Val::Px(10.));
 const NORMAL_BUTTON: Srgba = BLACK;
 const HOVERED_BUTTON: Srgba = DARK_SLATE_GREY;
 const PRESSED_BUTTON: Srgba = GREY;
-const UI_CLICK_SOUND_EFFECT: &str = "ui_click.ogg";
+const DISABLED_BUTTON: Srgba = Srgba::rgb(0.1, 0.1, 0.1);
+const DISABLED_TEXT: Srgba = GREY;
+
+/// A menu opened on top of the main menu or the pause menu, which is hidden while it's open.
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SubMenu {
+    #[default]
+    None,
+    Controls,
+    Settings,
+}
+
+/// The button that keyboard and gamepad navigation is on. It's highlighted like a hovered
+/// button and activated with Enter or the A button.
+#[derive(Resource, Default)]
+pub struct MenuFocus {
+    focused: Option<Entity>,
+    /// Whether the focused button was activated this frame
+    activated: bool,
+    /// Turns off navigation while a menu needs the keyboard for something else
+    pub blocked: bool,
+}
 
-#[derive(Event)]
-pub struct UiClickEvent;
+/// Hidden while a [`SubMenu`] is open.
+#[derive(Component)]
+pub struct HiddenInSubMenu;
+
+/// Buttons with this component don't react to being hovered or clicked.
+#[derive(Component)]
+pub struct Disabled;
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<UiClickEvent>()
-            .add_systems(PostUpdate, play_ui_click_audio);
+        app.add_plugins((
+            controls_menu::ControlsMenuPlugin,
+            settings_menu::SettingsMenuPlugin,
+        ))
+        .init_resource::<MenuFocus>()
+        .init_state::<SubMenu>()
+        .add_systems(OnExit(GameState::MainMenu), close_sub_menu)
+        .add_systems(OnExit(IsPaused::Paused), close_sub_menu)
+        .add_systems(
+            Update,
+            hide_menus_in_sub_menu.run_if(state_changed::<SubMenu>),
+        )
+        .add_systems(PreUpdate, navigate_menus.after(InputSystem))
+        .add_systems(PostUpdate, highlight_focused_button);
     }
 }
 
@@ -71,6 +113,21 @@ pub fn spawn_button<'a>(
     cmds
 }
 
+pub fn spawn_text<'a>(
+    parent: &'a mut ChildBuilder,
+    text: impl Into<String>,
+    font_size: f32,
+) -> EntityCommands<'a> {
+    parent.spawn(TextBundle::from_section(
+        text,
+        TextStyle {
+            font_size,
+            color: WHITE.into(),
+            ..default()
+        },
+    ))
+}
+
 pub fn spawn_sized_box(parent: &mut ChildBuilder, width: Val, height: Val) {
     parent.spawn(NodeBundle {
         style: Style {
@@ -82,40 +139,152 @@ pub fn spawn_sized_box(parent: &mut ChildBuilder, width: Val, height: Val) {
     });
 }
 
-fn play_ui_click_audio(
-    mut ui_click_event_reader: EventReader<UiClickEvent>,
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+fn close_sub_menu(mut next_sub_menu: ResMut<NextState<SubMenu>>) {
+    next_sub_menu.set(SubMenu::None);
+}
+
+fn hide_menus_in_sub_menu(
+    sub_menu: Res<State<SubMenu>>,
+    mut menus: Query<&mut Visibility, With<HiddenInSubMenu>>,
 ) {
-    for _ in ui_click_event_reader.read() {
-        commands.spawn(AudioBundle {
-            source: asset_server.load(UI_CLICK_SOUND_EFFECT),
-            settings: PlaybackSettings {
-                mode: PlaybackMode::Despawn,
-                volume: Volume::new(0.2),
-                spatial: false,
-                ..default()
-            },
-        });
+    for mut visibility in &mut menus {
+        *visibility = match **sub_menu {
+            SubMenu::None => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
     }
 }
 
+/// Moves the focus to the closest visible button in the direction of the pressed arrow key or
+/// D-pad button.
+#[allow(clippy::type_complexity)]
+fn navigate_menus(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut focus: ResMut<MenuFocus>,
+    buttons: Query<(Entity, &GlobalTransform, &ViewVisibility), (With<Button>, Without<Disabled>)>,
+) {
+    if focus.activated {
+        focus.activated = false;
+    }
+    if focus.blocked {
+        return;
+    }
+
+    let just_pressed = |key, button| {
+        keyboard_input.just_pressed(key)
+            || gamepads
+                .iter()
+                .any(|gamepad| gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button)))
+    };
+    // UI coordinates point down
+    let direction = [
+        (KeyCode::ArrowUp, GamepadButtonType::DPadUp, Vec2::NEG_Y),
+        (KeyCode::ArrowDown, GamepadButtonType::DPadDown, Vec2::Y),
+        (KeyCode::ArrowLeft, GamepadButtonType::DPadLeft, Vec2::NEG_X),
+        (KeyCode::ArrowRight, GamepadButtonType::DPadRight, Vec2::X),
+    ]
+    .into_iter()
+    .find(|(key, button, _)| just_pressed(*key, *button))
+    .map(|(_, _, direction)| direction);
+    let activate = just_pressed(KeyCode::Enter, GamepadButtonType::South)
+        || keyboard_input.just_pressed(KeyCode::NumpadEnter);
+    if direction.is_none() && !activate {
+        return;
+    }
+
+    let visible_buttons = || {
+        buttons
+            .iter()
+            .filter(|(_, _, visibility)| visibility.get())
+            .map(|(entity, transform, _)| (entity, transform.translation().truncate()))
+    };
+    let current = focus
+        .focused
+        .and_then(|focused| visible_buttons().find(|(entity, _)| *entity == focused));
+    let Some((current, position)) = current else {
+        // start at the top left button
+        focus.focused = visible_buttons()
+            .min_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)))
+            .map(|(entity, _)| entity);
+        return;
+    };
+
+    let Some(direction) = direction else {
+        focus.activated = true;
+        return;
+    };
+    // buttons straight ahead are preferred over closer ones off to the side
+    let next = visible_buttons()
+        .filter(|(entity, _)| *entity != current)
+        .filter_map(|(entity, other)| {
+            let offset = other - position;
+            let ahead = offset.dot(direction);
+            let aside = offset.perp_dot(direction).abs();
+            (ahead > 1.).then_some((entity, ahead + 2. * aside))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+    if let Some((next, _)) = next {
+        focus.focused = Some(next);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn highlight_focused_button(
+    focus: Res<MenuFocus>,
+    mut previous: Local<Option<Entity>>,
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), (With<Button>, Without<Disabled>)>,
+) {
+    if *previous != focus.focused {
+        if let Some(Ok((Interaction::None, mut bg))) =
+            previous.map(|entity| buttons.get_mut(entity))
+        {
+            *bg = NORMAL_BUTTON.into();
+        }
+        *previous = focus.focused;
+    }
+
+    if let Some(Ok((Interaction::None, mut bg))) =
+        focus.focused.map(|entity| buttons.get_mut(entity))
+    {
+        bg.set_if_neq(HOVERED_BUTTON.into());
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn button_interaction<C: Component>(
     mouse_input: Res<ButtonInput<MouseButton>>,
+    mut focus: ResMut<MenuFocus>,
     mut interaction_query: Query<
-        (Entity, &Interaction, &mut BackgroundColor),
+        (Entity, &Interaction, &mut BackgroundColor, Has<Disabled>),
         (Changed<Interaction>, With<C>),
     >,
-    mut ui_click_event_writer: EventWriter<UiClickEvent>,
+    focusable_query: Query<(), (With<C>, Without<Disabled>)>,
+    mut play_sfx_writer: EventWriter<PlaySfx>,
 ) -> ButtonInteractionResult {
-    for (entity, interaction, mut bg) in &mut interaction_query {
+    if focus.activated {
+        if let Some(focused) = focus.focused.filter(|e| focusable_query.contains(*e)) {
+            focus.activated = false;
+            play_sfx_writer.send(PlaySfx(Sfx::UiClick));
+            return Some(focused);
+        }
+    }
+
+    for (entity, interaction, mut bg, is_disabled) in &mut interaction_query {
+        if is_disabled {
+            continue;
+        }
+
         match interaction {
+            Interaction::None if focus.focused == Some(entity) => *bg = HOVERED_BUTTON.into(),
             Interaction::None => *bg = NORMAL_BUTTON.into(),
             Interaction::Hovered => {
                 *bg = HOVERED_BUTTON.into();
+                focus.focused = Some(entity);
 
                 if mouse_input.just_released(MouseButton::Left) {
-                    ui_click_event_writer.send(UiClickEvent);
+                    play_sfx_writer.send(PlaySfx(Sfx::UiClick));
                     return Some(entity);
                 }
             }
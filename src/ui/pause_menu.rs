@@ -13,6 +13,8 @@ impl Plugin for PauseMenuPlugin {
                 Update,
                 (
                     button_interaction::<ResumeButton>.pipe(resume_button_system),
+                    button_interaction::<ControlsButton>.pipe(controls_button_system),
+                    button_interaction::<SettingsButton>.pipe(settings_button_system),
                     button_interaction::<ExitToMenuButton>.pipe(exit_to_main_menu_button_system),
                 )
                     .run_if(in_state(IsPaused::Paused)),
@@ -26,12 +28,18 @@ struct PauseMenu;
 #[derive(Component)]
 struct ResumeButton;
 
+#[derive(Component)]
+struct ControlsButton;
+
+#[derive(Component)]
+struct SettingsButton;
+
 #[derive(Component)]
 struct ExitToMenuButton;
 
 fn setup_pause_menu(mut commands: Commands) {
     spawn_root_node(&mut commands)
-        .insert(PauseMenu)
+        .insert((PauseMenu, HiddenInSubMenu))
         .with_children(|parent| {
             parent
                 .spawn(NodeBundle {
@@ -49,6 +57,10 @@ fn setup_pause_menu(mut commands: Commands) {
                 .with_children(|parent| {
                     spawn_button(parent, "Resume").insert(ResumeButton);
                     spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+                    spawn_button(parent, "Controls").insert(ControlsButton);
+                    spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+                    spawn_button(parent, "Settings").insert(SettingsButton);
+                    spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
                     spawn_button(parent, "Exit to Menu").insert(ExitToMenuButton);
                 });
         });
@@ -78,6 +90,24 @@ fn resume_button_system(
     });
 }
 
+fn settings_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_sub_menu: ResMut<NextState<SubMenu>>,
+) {
+    if released.is_some() {
+        next_sub_menu.set(SubMenu::Settings);
+    }
+}
+
+fn controls_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_sub_menu: ResMut<NextState<SubMenu>>,
+) {
+    if released.is_some() {
+        next_sub_menu.set(SubMenu::Controls);
+    }
+}
+
 fn exit_to_main_menu_button_system(
     In(released): In<ButtonInteractionResult>,
     mut next_game_state: ResMut<NextState<GameState>>,
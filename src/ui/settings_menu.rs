@@ -0,0 +1,379 @@
+use bevy::{color::palettes::css::*, prelude::*, window::PrimaryWindow};
+
+use super::*;
+use crate::settings::{save_settings, Settings, SettingsPath, VolumeSettings};
+
+/// How much the volume changes when clicking the - and + buttons
+const VOLUME_STEP: f32 = 0.1;
+const SLIDER_WIDTH: Val = Val::Px(300.);
+
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(SubMenu::Settings), setup_settings_menu)
+            .add_systems(OnExit(SubMenu::Settings), cleanup_settings_menu)
+            .add_systems(
+                Update,
+                (
+                    button_interaction::<VolumeButton>.pipe(volume_button_system),
+                    button_interaction::<ToggleButton>.pipe(toggle_button_system),
+                    button_interaction::<BackButton>.pipe(back_button_system),
+                    volume_slider_system,
+                    close_on_escape,
+                    update_volume_sliders,
+                    update_toggle_buttons,
+                )
+                    .chain()
+                    .run_if(in_state(SubMenu::Settings)),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VolumeChannel {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl VolumeChannel {
+    const ALL: [VolumeChannel; 3] = [
+        VolumeChannel::Master,
+        VolumeChannel::Music,
+        VolumeChannel::Sfx,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            VolumeChannel::Master => "Master",
+            VolumeChannel::Music => "Music",
+            VolumeChannel::Sfx => "Sound Effects",
+        }
+    }
+
+    fn get(self, volume: &VolumeSettings) -> f32 {
+        match self {
+            VolumeChannel::Master => volume.master,
+            VolumeChannel::Music => volume.music,
+            VolumeChannel::Sfx => volume.sfx,
+        }
+    }
+
+    fn get_mut(self, volume: &mut VolumeSettings) -> &mut f32 {
+        match self {
+            VolumeChannel::Master => &mut volume.master,
+            VolumeChannel::Music => &mut volume.music,
+            VolumeChannel::Sfx => &mut volume.sfx,
+        }
+    }
+}
+
+/// A setting that is either on or off.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Toggle {
+    ScreenShake,
+    InstantRespawn,
+}
+
+impl Toggle {
+    const ALL: [Toggle; 2] = [Toggle::ScreenShake, Toggle::InstantRespawn];
+
+    fn name(self) -> &'static str {
+        match self {
+            Toggle::ScreenShake => "Screen Shake",
+            Toggle::InstantRespawn => "Instant Respawn",
+        }
+    }
+
+    fn get(self, settings: &Settings) -> bool {
+        match self {
+            Toggle::ScreenShake => settings.screen_shake,
+            Toggle::InstantRespawn => settings.instant_respawn,
+        }
+    }
+
+    fn get_mut(self, settings: &mut Settings) -> &mut bool {
+        match self {
+            Toggle::ScreenShake => &mut settings.screen_shake,
+            Toggle::InstantRespawn => &mut settings.instant_respawn,
+        }
+    }
+
+    fn label(self, settings: &Settings) -> String {
+        let state = if self.get(settings) { "On" } else { "Off" };
+        format!("{}: {state}", self.name())
+    }
+}
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component)]
+struct VolumeButton {
+    channel: VolumeChannel,
+    step: f32,
+}
+
+/// The track of a volume slider, click or drag it to set the volume.
+#[derive(Component)]
+struct VolumeSlider(VolumeChannel);
+
+/// The filled part of a [`VolumeSlider`].
+#[derive(Component)]
+struct VolumeSliderFill(VolumeChannel);
+
+#[derive(Component)]
+struct VolumeText(VolumeChannel);
+
+#[derive(Component)]
+struct ToggleButton(Toggle);
+
+#[derive(Component)]
+struct BackButton;
+
+fn setup_settings_menu(mut commands: Commands, settings: Res<Settings>) {
+    spawn_root_node(&mut commands)
+        .insert(SettingsMenu)
+        .insert(BackgroundColor(BLACK.with_alpha(0.8).into()))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font_size: 50.,
+                    color: WHITE.into(),
+                    ..default()
+                },
+            ));
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(30.));
+
+            for channel in VolumeChannel::ALL {
+                let value = channel.get(&settings.volume);
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: UiRect::vertical(Val::Px(8.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        let text_style = TextStyle {
+                            font_size: 25.,
+                            color: WHITE.into(),
+                            ..default()
+                        };
+                        let small_button_style = Style {
+                            width: Val::Px(50.),
+                            padding: BUTTON_PADDING,
+                            margin: UiRect::horizontal(Val::Px(10.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        };
+
+                        parent.spawn(
+                            TextBundle::from_section(channel.name(), text_style.clone())
+                                .with_style(Style {
+                                    width: Val::Px(200.),
+                                    ..default()
+                                }),
+                        );
+                        spawn_button(parent, "-")
+                            .insert(VolumeButton {
+                                channel,
+                                step: -VOLUME_STEP,
+                            })
+                            .insert(small_button_style.clone());
+                        parent
+                            .spawn((
+                                NodeBundle {
+                                    style: Style {
+                                        width: SLIDER_WIDTH,
+                                        height: Val::Px(20.),
+                                        ..default()
+                                    },
+                                    background_color: DARK_SLATE_GREY.into(),
+                                    ..default()
+                                },
+                                Interaction::default(),
+                                VolumeSlider(channel),
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            width: Val::Percent(value * 100.),
+                                            height: Val::Percent(100.),
+                                            ..default()
+                                        },
+                                        background_color: WHITE.into(),
+                                        ..default()
+                                    },
+                                    VolumeSliderFill(channel),
+                                ));
+                            });
+                        spawn_button(parent, "+")
+                            .insert(VolumeButton {
+                                channel,
+                                step: VOLUME_STEP,
+                            })
+                            .insert(small_button_style);
+                        parent.spawn((
+                            TextBundle::from_section(format_volume(value), text_style).with_style(
+                                Style {
+                                    width: Val::Px(80.),
+                                    ..default()
+                                },
+                            ),
+                            VolumeText(channel),
+                        ));
+                    });
+            }
+
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(20.));
+            for toggle in Toggle::ALL {
+                spawn_button(parent, toggle.label(&settings))
+                    .insert(ToggleButton(toggle))
+                    .insert(Style {
+                        width: Val::Px(300.),
+                        padding: BUTTON_PADDING,
+                        margin: UiRect::vertical(Val::Px(5.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    });
+            }
+            spawn_sized_box(parent, Val::DEFAULT, Val::Px(30.));
+            spawn_button(parent, "Back").insert(BackButton);
+        });
+}
+
+fn cleanup_settings_menu(mut commands: Commands, entities: Query<Entity, With<SettingsMenu>>) {
+    for entity in &entities {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn format_volume(value: f32) -> String {
+    format!("{:.0}%", value * 100.)
+}
+
+fn volume_button_system(
+    In(released): In<ButtonInteractionResult>,
+    buttons: Query<&VolumeButton>,
+    mut settings: ResMut<Settings>,
+    settings_path: Res<SettingsPath>,
+) {
+    let Some(button) = released.and_then(|entity| buttons.get(entity).ok()) else {
+        return;
+    };
+
+    let volume = button.channel.get_mut(&mut settings.volume);
+    // round so repeated steps don't accumulate float errors
+    *volume = ((*volume + button.step) / VOLUME_STEP).round() * VOLUME_STEP;
+    *volume = volume.clamp(0., 1.);
+    save_settings(&settings, &settings_path);
+}
+
+/// Sets the volume to where the slider is clicked or dragged to. The settings are saved when the
+/// mouse is released.
+fn volume_slider_system(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    sliders: Query<(&Interaction, &Node, &GlobalTransform, &VolumeSlider)>,
+    mut settings: ResMut<Settings>,
+    settings_path: Res<SettingsPath>,
+    mut dragging: Local<bool>,
+) {
+    if *dragging && mouse_input.just_released(MouseButton::Left) {
+        *dragging = false;
+        save_settings(&settings, &settings_path);
+    }
+
+    let Some(cursor) = window.get_single().ok().and_then(Window::cursor_position) else {
+        return;
+    };
+    for (interaction, node, transform, slider) in &sliders {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let rect = node.logical_rect(transform);
+        let value = ((cursor.x - rect.min.x) / rect.width()).clamp(0., 1.);
+        // snap to whole percents
+        let value = (value * 100.).round() / 100.;
+        if slider.0.get(&settings.volume) != value {
+            *slider.0.get_mut(&mut settings.volume) = value;
+        }
+        *dragging = true;
+    }
+}
+
+fn update_volume_sliders(
+    settings: Res<Settings>,
+    mut fills: Query<(&mut Style, &VolumeSliderFill)>,
+    mut texts: Query<(&mut Text, &VolumeText)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (mut style, fill) in &mut fills {
+        style.width = Val::Percent(fill.0.get(&settings.volume) * 100.);
+    }
+    for (mut text, volume_text) in &mut texts {
+        text.sections[0].value = format_volume(volume_text.0.get(&settings.volume));
+    }
+}
+
+fn toggle_button_system(
+    In(released): In<ButtonInteractionResult>,
+    buttons: Query<&ToggleButton>,
+    mut settings: ResMut<Settings>,
+    settings_path: Res<SettingsPath>,
+) {
+    let Some(button) = released.and_then(|entity| buttons.get(entity).ok()) else {
+        return;
+    };
+
+    let enabled = button.0.get_mut(&mut settings);
+    *enabled = !*enabled;
+    save_settings(&settings, &settings_path);
+}
+
+fn update_toggle_buttons(
+    settings: Res<Settings>,
+    buttons: Query<(&ToggleButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (button, children) in &buttons {
+        let mut iter = texts.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = button.0.label(&settings);
+        }
+    }
+}
+
+fn close_on_escape(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut next_sub_menu: ResMut<NextState<SubMenu>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_sub_menu.set(SubMenu::None);
+    }
+}
+
+fn back_button_system(
+    In(released): In<ButtonInteractionResult>,
+    mut next_sub_menu: ResMut<NextState<SubMenu>>,
+) {
+    if released.is_some() {
+        next_sub_menu.set(SubMenu::None);
+    }
+}